@@ -51,8 +51,14 @@ pub fn nested_map_from_file<T: AsRef<Path>>(
         )
     })?;
     let f = BufReader::new(f);
-    let content: Vec<String> = f.lines().map(std::result::Result::unwrap).collect();
-    for i in 0..(content.len() - 1) {
+    let content: Vec<String> = f.lines().collect::<Result<_, _>>().map_err(|e| {
+        debug!(
+            "failed to read file ({:#?}): {}",
+            path.as_ref().as_os_str(),
+            e
+        )
+    })?;
+    for i in 0..content.len().saturating_sub(1) {
         if let Some(keys) = content.get(i) {
             if let Some(values) = content.get(i + 1) {
                 let keys: Vec<&str> = keys.trim().split_whitespace().collect();
@@ -73,3 +79,60 @@ pub fn nested_map_from_file<T: AsRef<Path>>(
     }
     Ok(ret)
 }
+
+/// Parse a file with one key and value per line, as in `/proc/net/snmp6`,
+/// into the same layout as `nested_map_from_file`. Each key is split after
+/// the first `6` into the section, with a trailing `:`, and the field:
+/// `Ip6InReceives` is the `InReceives` field of `Ip6:`
+pub fn snmp6_map_from_file<T: AsRef<Path>>(
+    path: T,
+) -> Result<HashMap<String, HashMap<String, u64>>, ()> {
+    let mut ret = HashMap::<String, HashMap<String, u64>>::new();
+    let f = File::open(&path).map_err(|e| {
+        debug!(
+            "failed to open file ({:#?}): {}",
+            path.as_ref().as_os_str(),
+            e
+        )
+    })?;
+    let f = BufReader::new(f);
+    for line in f.lines() {
+        let line = line.map_err(|e| {
+            debug!(
+                "failed to read file ({:#?}): {}",
+                path.as_ref().as_os_str(),
+                e
+            )
+        })?;
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() != 2 {
+            continue;
+        }
+        if let Some(index) = parts[0].find('6') {
+            let (section, field) = parts[0].split_at(index + 1);
+            if field.is_empty() {
+                continue;
+            }
+            let value: u64 = parts[1].parse().unwrap_or(0);
+            ret.entry(format!("{}:", section))
+                .or_default()
+                .insert(field.to_string(), value);
+        }
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_snmp6() {
+        let data = snmp6_map_from_file("tests/data/proc/net/snmp6").unwrap();
+        assert_eq!(data["Ip6:"]["InReceives"], 5017);
+        assert_eq!(data["Icmp6:"]["OutMsgs"], 96);
+        assert_eq!(data["Icmp6:"]["InType134"], 12);
+        assert_eq!(data["Udp6:"]["RcvbufErrors"], 4);
+        assert!(data.get("Ip6InReceives:").is_none());
+    }
+}
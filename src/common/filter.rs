@@ -0,0 +1,58 @@
+// Copyright 2019 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use regex::Regex;
+
+/// Selects devices, such as disks or network interfaces, by name with include
+/// and exclude patterns
+#[derive(Clone, Debug)]
+pub struct Filter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl Filter {
+    /// Compiles the patterns, returning the first which is not a valid regular
+    /// expression along with the reason
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self, String> {
+        let compile = |patterns: &[String]| -> Result<Vec<Regex>, String> {
+            patterns
+                .iter()
+                .map(|pattern| Regex::new(pattern).map_err(|e| format!("{}: {}", pattern, e)))
+                .collect()
+        };
+        Ok(Self {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    /// Returns true if the name matches any include pattern, or there are
+    /// none, and does not match any exclude pattern
+    pub fn matches(&self, name: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|re| re.is_match(name)))
+            && !self.exclude.iter().any(|re| re.is_match(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches() {
+        let filter = Filter::new(&[], &[]).unwrap();
+        assert!(filter.matches("sda"));
+        let filter = Filter::new(
+            &["^nvme".to_string(), "^sd".to_string()],
+            &["^sdb$".to_string()],
+        )
+        .unwrap();
+        assert!(filter.matches("nvme0n1"));
+        assert!(filter.matches("sda"));
+        assert!(!filter.matches("sdb"));
+        assert!(!filter.matches("md0"));
+        assert!(Filter::new(&["(".to_string()], &[]).is_err());
+    }
+}
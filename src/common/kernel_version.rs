@@ -6,6 +6,7 @@ use crate::common::file::*;
 use regex::Regex;
 
 use std::fmt;
+use std::path::Path;
 
 pub struct Version {
     pub major: usize,
@@ -13,8 +14,8 @@ pub struct Version {
     pub patch: usize,
 }
 
-pub fn get_version() -> Version {
-    let info = string_from_file("/proc/version").unwrap();
+pub fn get_version(proc_root: &Path) -> Version {
+    let info = string_from_file(proc_root.join("version")).unwrap();
     let regex = Regex::new(r"(\d+)\.(\d+)\.(\d+)").unwrap();
     let captures = regex.captures(&info).unwrap();
     let major: usize = captures.get(1).unwrap().as_str().parse().unwrap();
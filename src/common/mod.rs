@@ -7,16 +7,19 @@
 extern crate sysconf;
 
 pub mod file;
+pub mod filter;
 pub mod http;
 pub mod kernel_version;
 pub mod net;
 
+pub use self::filter::Filter;
 pub use self::http::http_get;
 
 use logger::*;
 use metrics::Percentile;
 
 use std::io::BufRead;
+use std::path::Path;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const NAME: &str = env!("CARGO_PKG_NAME");
@@ -47,6 +50,7 @@ pub fn nanos_per_tick() -> u64 {
 pub const CONTAINER_REFRESH: u64 = MINUTE;
 pub const HTTP_TIMEOUT: u64 = 500 * MILLISECOND;
 pub const POLL_DELAY: u64 = 50 * MILLISECOND;
+pub const SHUTDOWN_TIMEOUT: u64 = 5 * SECOND;
 pub const SECTOR_SIZE: u64 = 512; //bytes per sector
 
 // reported percentiles
@@ -59,10 +63,10 @@ pub const PERCENTILES: &[Percentile] = &[
 ];
 
 /// helper function to discover the number of hardware threads
-pub fn hardware_threads() -> Result<u64, ()> {
-    let path = "/sys/devices/system/cpu/present";
-    let f =
-        std::fs::File::open(path).map_err(|e| debug!("failed to open file ({:?}): {}", path, e))?;
+pub fn hardware_threads(sys_root: &Path) -> Result<u64, ()> {
+    let path = sys_root.join("devices/system/cpu/present");
+    let f = std::fs::File::open(&path)
+        .map_err(|e| debug!("failed to open file ({:?}): {}", path, e))?;
     let mut f = std::io::BufReader::new(f);
 
     let mut line = String::new();
@@ -4,19 +4,20 @@
 
 use super::file;
 use logger::*;
+use std::path::Path;
 use std::process;
 use walkdir::WalkDir;
 
 /// return a named statistic for a given interface
-pub fn read_network_stat(nic: &str, stat: &str) -> Result<u64, ()> {
-    let path = format!("/sys/class/net/{}/statistics/{}", nic, stat);
+pub fn read_network_stat(sys_root: &Path, nic: &str, stat: &str) -> Result<u64, ()> {
+    let path = sys_root.join(format!("class/net/{}/statistics/{}", nic, stat));
     file::file_as_u64(&path)
 }
 
 /// returns a `Vec` of interface names
-pub fn get_network_interfaces() -> Result<Vec<String>, ()> {
+pub fn get_network_interfaces(sys_root: &Path) -> Result<Vec<String>, ()> {
     let mut result = Vec::new();
-    for entry in WalkDir::new("/sys/class/net/").max_depth(1) {
+    for entry in WalkDir::new(sys_root.join("class/net")).max_depth(1) {
         if let Ok(entry) = entry {
             if let Some(s) = entry.file_name().to_str() {
                 result.push(s.to_owned());
@@ -30,14 +31,26 @@ pub fn get_network_interfaces() -> Result<Vec<String>, ()> {
     }
 }
 
-// determine if a NIC is active based on operstate
-pub fn is_nic_active(nic: &str) -> bool {
+// determine if a NIC is active based on operstate. interfaces which do not
+// track their carrier, such as tunnels, report an `unknown` state
+pub fn is_nic_active(sys_root: &Path, nic: &str) -> bool {
     trace!("checking state: {}", nic);
-    let path = format!("/sys/class/net/{}/operstate", nic);
-    let operstate = file::string_from_file(&path).unwrap();
+    let path = sys_root.join(format!("class/net/{}/operstate", nic));
+    let operstate = file::string_from_file(&path).unwrap_or_default();
     let operstate = operstate.trim();
     trace!("nic: {} is in state: ({})", nic, operstate);
-    operstate == "up"
+    operstate == "up" || operstate == "unknown"
+}
+
+/// returns the name of the interface which the NIC is enslaved to, such as
+/// the bond of which it is a member, based on its `master` link
+pub fn nic_master(sys_root: &Path, nic: &str) -> Option<String> {
+    let path = sys_root.join(format!("class/net/{}/master", nic));
+    let master = std::fs::read_link(&path).ok()?;
+    master
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.to_owned())
 }
 
 pub struct FqCodelCounters {
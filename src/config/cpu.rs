@@ -3,41 +3,116 @@
 // http://www.apache.org/licenses/LICENSE-2.0
 
 use crate::config::*;
-use crate::samplers::cpu::CpuStatistic;
-use core::sync::atomic::{AtomicBool, Ordering};
+use crate::samplers::cpu::{CpuStatistic, SystemStatistic};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct Cpu {
     #[serde(default = "default_enabled")]
-    enabled: AtomicBool,
+    enabled: bool,
+    interval: Option<usize>,
     #[serde(default = "default_statistics")]
     statistics: Vec<CpuStatistic>,
+    #[serde(default = "default_system_statistics")]
+    system_statistics: Vec<SystemStatistic>,
+    #[serde(default = "default_per_cpu")]
+    per_cpu: bool,
+    #[serde(default = "default_per_socket")]
+    per_socket: bool,
+    #[serde(default = "default_per_node")]
+    per_node: bool,
+    #[serde(default = "default_per_irq")]
+    per_irq: bool,
 }
 
 impl Default for Cpu {
     fn default() -> Cpu {
         Cpu {
             enabled: default_enabled(),
+            interval: None,
             statistics: default_statistics(),
+            system_statistics: default_system_statistics(),
+            per_cpu: default_per_cpu(),
+            per_socket: default_per_socket(),
+            per_node: default_per_node(),
+            per_irq: default_per_irq(),
         }
     }
 }
 
-fn default_enabled() -> AtomicBool {
-    AtomicBool::new(false)
+fn default_enabled() -> bool {
+    false
 }
 
 fn default_statistics() -> Vec<CpuStatistic> {
     vec![CpuStatistic::User, CpuStatistic::System, CpuStatistic::Idle]
 }
 
+fn default_system_statistics() -> Vec<SystemStatistic> {
+    vec![
+        SystemStatistic::ContextSwitches,
+        SystemStatistic::Interrupts,
+        SystemStatistic::Forks,
+        SystemStatistic::ProcessesRunning,
+        SystemStatistic::ProcessesBlocked,
+    ]
+}
+
+fn default_per_cpu() -> bool {
+    false
+}
+
+fn default_per_socket() -> bool {
+    false
+}
+
+fn default_per_node() -> bool {
+    false
+}
+
+fn default_per_irq() -> bool {
+    false
+}
+
 impl Cpu {
     pub fn enabled(&self) -> bool {
-        self.enabled.load(Ordering::Relaxed)
+        self.enabled
     }
 
     pub fn statistics(&self) -> Vec<CpuStatistic> {
         self.statistics.clone()
     }
+
+    pub fn system_statistics(&self) -> Vec<SystemStatistic> {
+        self.system_statistics.clone()
+    }
+
+    /// whether to export each statistic for every CPU in addition to the
+    /// total across CPUs
+    pub fn per_cpu(&self) -> bool {
+        self.per_cpu
+    }
+
+    /// whether to export each statistic summed across the CPUs of each socket
+    pub fn per_socket(&self) -> bool {
+        self.per_socket
+    }
+
+    /// whether to export each statistic summed across the CPUs of each NUMA
+    /// node
+    pub fn per_node(&self) -> bool {
+        self.per_node
+    }
+
+    /// whether to export the interrupts serviced for each IRQ in addition to
+    /// the total
+    pub fn per_irq(&self) -> bool {
+        self.per_irq
+    }
+}
+
+impl SamplerConfig for Cpu {
+    fn interval(&self) -> Option<usize> {
+        self.interval
+    }
 }
@@ -0,0 +1,54 @@
+// Copyright 2019 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use crate::config::*;
+use crate::samplers::cpupower::Statistic;
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Cpupower {
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+    interval: Option<usize>,
+    #[serde(default = "default_statistics")]
+    statistics: Vec<Statistic>,
+}
+
+impl Default for Cpupower {
+    fn default() -> Cpupower {
+        Cpupower {
+            enabled: default_enabled(),
+            interval: None,
+            statistics: default_statistics(),
+        }
+    }
+}
+
+fn default_enabled() -> bool {
+    false
+}
+
+fn default_statistics() -> Vec<Statistic> {
+    vec![
+        Statistic::Frequency,
+        Statistic::CstateTime,
+        Statistic::CstateUsage,
+    ]
+}
+
+impl Cpupower {
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn statistics(&self) -> Vec<Statistic> {
+        self.statistics.clone()
+    }
+}
+
+impl SamplerConfig for Cpupower {
+    fn interval(&self) -> Option<usize> {
+        self.interval
+    }
+}
@@ -3,29 +3,112 @@
 // http://www.apache.org/licenses/LICENSE-2.0
 
 use crate::config::*;
-use core::sync::atomic::{AtomicBool, Ordering};
+use crate::samplers::disk::{DerivedStatistic, DeviceType, Statistic};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct Disk {
     #[serde(default = "default_enabled")]
-    enabled: AtomicBool,
+    enabled: bool,
+    interval: Option<usize>,
+    #[serde(default = "default_statistics")]
+    statistics: Vec<Statistic>,
+    #[serde(default = "default_derived_statistics")]
+    derived_statistics: Vec<DerivedStatistic>,
+    #[serde(default = "default_per_device")]
+    per_device: bool,
+    #[serde(default = "default_device_types")]
+    device_types: Vec<DeviceType>,
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
 }
 
 impl Default for Disk {
     fn default() -> Disk {
         Disk {
             enabled: default_enabled(),
+            interval: None,
+            statistics: default_statistics(),
+            derived_statistics: default_derived_statistics(),
+            per_device: default_per_device(),
+            device_types: default_device_types(),
+            include: Vec::new(),
+            exclude: Vec::new(),
         }
     }
 }
 
-fn default_enabled() -> AtomicBool {
-    AtomicBool::new(false)
+fn default_enabled() -> bool {
+    false
+}
+
+fn default_statistics() -> Vec<Statistic> {
+    vec![
+        Statistic::BandwidthRead,
+        Statistic::BandwidthWrite,
+        Statistic::OperationsRead,
+        Statistic::OperationsWrite,
+    ]
+}
+
+fn default_derived_statistics() -> Vec<DerivedStatistic> {
+    vec![
+        DerivedStatistic::Utilization,
+        DerivedStatistic::AwaitRead,
+        DerivedStatistic::AwaitWrite,
+        DerivedStatistic::RequestSize,
+        DerivedStatistic::QueueSize,
+    ]
+}
+
+fn default_per_device() -> bool {
+    false
+}
+
+fn default_device_types() -> Vec<DeviceType> {
+    vec![DeviceType::Disk]
 }
 
 impl Disk {
     pub fn enabled(&self) -> bool {
-        self.enabled.load(Ordering::Relaxed)
+        self.enabled
+    }
+
+    pub fn statistics(&self) -> Vec<Statistic> {
+        self.statistics.clone()
+    }
+
+    pub fn derived_statistics(&self) -> Vec<DerivedStatistic> {
+        self.derived_statistics.clone()
+    }
+
+    /// whether to export each statistic for every block device in addition
+    /// to the total across devices
+    pub fn per_device(&self) -> bool {
+        self.per_device
+    }
+
+    /// the kinds of block device which are monitored
+    pub fn device_types(&self) -> Vec<DeviceType> {
+        self.device_types.clone()
+    }
+
+    /// patterns for the names of devices to monitor. all devices of the
+    /// configured types are monitored if this is empty
+    pub fn include(&self) -> &[String] {
+        &self.include
+    }
+
+    /// patterns for the names of devices to ignore, even if included
+    pub fn exclude(&self) -> &[String] {
+        &self.exclude
+    }
+}
+
+impl SamplerConfig for Disk {
+    fn interval(&self) -> Option<usize> {
+        self.interval
     }
 }
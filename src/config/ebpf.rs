@@ -3,21 +3,21 @@
 // http://www.apache.org/licenses/LICENSE-2.0
 
 use crate::config::*;
-use core::sync::atomic::{AtomicBool, Ordering};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct Ebpf {
     #[serde(default = "default")]
-    all: AtomicBool,
+    all: bool,
     #[serde(default = "default")]
-    block: AtomicBool,
+    block: bool,
     #[serde(default = "default")]
-    ext4: AtomicBool,
+    ext4: bool,
     #[serde(default = "default")]
-    scheduler: AtomicBool,
+    scheduler: bool,
     #[serde(default = "default")]
-    xfs: AtomicBool,
+    xfs: bool,
+    interval: Option<usize>,
 }
 
 impl Default for Ebpf {
@@ -28,32 +28,39 @@ impl Default for Ebpf {
             ext4: default(),
             scheduler: default(),
             xfs: default(),
+            interval: None,
         }
     }
 }
 
-fn default() -> AtomicBool {
-    AtomicBool::new(false)
+fn default() -> bool {
+    false
 }
 
 impl Ebpf {
     #[allow(dead_code)]
     pub fn block(&self) -> bool {
-        self.all.load(Ordering::Relaxed) || self.block.load(Ordering::Relaxed)
+        self.all || self.block
     }
 
     #[allow(dead_code)]
     pub fn ext4(&self) -> bool {
-        self.all.load(Ordering::Relaxed) || self.ext4.load(Ordering::Relaxed)
+        self.all || self.ext4
     }
 
     #[allow(dead_code)]
     pub fn scheduler(&self) -> bool {
-        self.all.load(Ordering::Relaxed) || self.scheduler.load(Ordering::Relaxed)
+        self.all || self.scheduler
     }
 
     #[allow(dead_code)]
     pub fn xfs(&self) -> bool {
-        self.all.load(Ordering::Relaxed) || self.xfs.load(Ordering::Relaxed)
+        self.all || self.xfs
+    }
+}
+
+impl SamplerConfig for Ebpf {
+    fn interval(&self) -> Option<usize> {
+        self.interval
     }
 }
@@ -3,25 +3,36 @@
 // http://www.apache.org/licenses/LICENSE-2.0
 
 use crate::config::*;
-use core::sync::atomic::{AtomicUsize, Ordering};
+use std::path::Path;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct General {
     listen: Option<String>,
     #[serde(with = "LevelDef")]
     #[serde(default = "default_logging_level")]
     logging: Level,
+    #[serde(default)]
+    passthrough: bool,
+    /// deprecated in favor of `[memcache] endpoint`
     memcache: Option<String>,
     #[serde(default = "default_interval")]
-    interval: AtomicUsize,
+    interval: usize,
     #[serde(default = "default_window")]
-    window: AtomicUsize,
+    window: usize,
     #[serde(default = "default_timeout")]
-    timeout: AtomicUsize,
+    timeout: usize,
     #[serde(default = "default_max_timeouts")]
-    max_timeouts: AtomicUsize,
+    max_timeouts: usize,
+    #[serde(default = "default_backoff")]
+    backoff: usize,
+    #[serde(default = "default_max_backoff")]
+    max_backoff: usize,
     stats_log: Option<String>,
+    #[serde(default = "default_proc_root")]
+    proc_root: String,
+    #[serde(default = "default_sys_root")]
+    sys_root: String,
 }
 
 impl General {
@@ -38,30 +49,61 @@ impl General {
     }
 
     pub fn interval(&self) -> usize {
-        self.interval.load(Ordering::Relaxed)
+        self.interval
     }
 
     pub fn window(&self) -> Duration {
-        Duration::new(self.window.load(Ordering::Relaxed) as u64, 0)
+        Duration::new(self.window as u64, 0)
     }
 
     pub fn timeout(&self) -> usize {
-        self.timeout.load(Ordering::Relaxed)
+        self.timeout
     }
 
     pub fn max_timeouts(&self) -> usize {
-        self.max_timeouts.load(Ordering::Relaxed)
+        self.max_timeouts
     }
 
-    pub fn memcache(&self) -> Option<String> {
-        self.memcache.clone()
+    pub fn backoff(&self) -> usize {
+        self.backoff
+    }
+
+    pub fn max_backoff(&self) -> usize {
+        self.max_backoff
+    }
+
+    /// when set, metrics are exposed under their original names: counters
+    /// have no `count` suffix and memcache stats are not namespaced
+    pub fn passthrough(&self) -> bool {
+        self.passthrough
+    }
+
+    /// takes the deprecated `memcache` setting, which has moved to the
+    /// `endpoint` of the `[memcache]` section
+    pub fn take_memcache(&mut self) -> Option<String> {
+        self.memcache.take()
     }
 
     pub fn stats_log(&self) -> Option<String> {
         self.stats_log.clone()
     }
+
+    /// the path at which procfs is mounted, so a host `/proc` may be
+    /// bind-mounted into a container or replaced with a fixture tree
+    pub fn proc_root(&self) -> &Path {
+        Path::new(&self.proc_root)
+    }
+
+    /// the path at which sysfs is mounted
+    pub fn sys_root(&self) -> &Path {
+        Path::new(&self.sys_root)
+    }
 }
 
+/// the `[general]` section configures the samplers which report on Rezolus
+/// itself, which sample at the general interval
+impl SamplerConfig for General {}
+
 impl Default for General {
     fn default() -> General {
         General {
@@ -71,26 +113,47 @@ impl Default for General {
             window: default_window(),
             timeout: default_timeout(),
             max_timeouts: default_max_timeouts(),
+            backoff: default_backoff(),
+            max_backoff: default_max_backoff(),
             stats_log: None,
+            proc_root: default_proc_root(),
+            sys_root: default_sys_root(),
+            passthrough: false,
             memcache: None,
         }
     }
 }
 
-fn default_interval() -> AtomicUsize {
-    AtomicUsize::new(1000)
+fn default_proc_root() -> String {
+    "/proc".to_string()
+}
+
+fn default_sys_root() -> String {
+    "/sys".to_string()
+}
+
+fn default_interval() -> usize {
+    1000
+}
+
+fn default_window() -> usize {
+    60
+}
+
+fn default_timeout() -> usize {
+    50
 }
 
-fn default_window() -> AtomicUsize {
-    AtomicUsize::new(60)
+fn default_max_timeouts() -> usize {
+    5
 }
 
-fn default_timeout() -> AtomicUsize {
-    AtomicUsize::new(50)
+fn default_backoff() -> usize {
+    1000
 }
 
-fn default_max_timeouts() -> AtomicUsize {
-    AtomicUsize::new(5)
+fn default_max_backoff() -> usize {
+    300_000
 }
 
 #[derive(Clone, Deserialize, Debug)]
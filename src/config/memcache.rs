@@ -0,0 +1,29 @@
+// Copyright 2019 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use crate::config::*;
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Memcache {
+    endpoint: Option<String>,
+    interval: Option<usize>,
+}
+
+impl Memcache {
+    /// the memcache instance to instrument. the sampler is enabled when set
+    pub fn endpoint(&self) -> Option<String> {
+        self.endpoint.clone()
+    }
+
+    pub fn set_endpoint(&mut self, endpoint: String) {
+        self.endpoint = Some(endpoint);
+    }
+}
+
+impl SamplerConfig for Memcache {
+    fn interval(&self) -> Option<usize> {
+        self.interval
+    }
+}
@@ -0,0 +1,63 @@
+// Copyright 2019 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use crate::config::*;
+use crate::samplers::memory::Statistic;
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Memory {
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+    interval: Option<usize>,
+    #[serde(default = "default_statistics")]
+    statistics: Vec<Statistic>,
+}
+
+impl Default for Memory {
+    fn default() -> Memory {
+        Memory {
+            enabled: default_enabled(),
+            interval: None,
+            statistics: default_statistics(),
+        }
+    }
+}
+
+fn default_enabled() -> bool {
+    false
+}
+
+fn default_statistics() -> Vec<Statistic> {
+    vec![
+        Statistic::Total,
+        Statistic::Free,
+        Statistic::Available,
+        Statistic::Buffers,
+        Statistic::Cached,
+        Statistic::Slab,
+        Statistic::Dirty,
+        Statistic::Writeback,
+        Statistic::AnonPages,
+        Statistic::Shmem,
+        Statistic::HugePagesTotal,
+        Statistic::HugePagesFree,
+    ]
+}
+
+impl Memory {
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn statistics(&self) -> Vec<Statistic> {
+        self.statistics.clone()
+    }
+}
+
+impl SamplerConfig for Memory {
+    fn interval(&self) -> Option<usize> {
+        self.interval
+    }
+}
@@ -3,17 +3,23 @@
 // http://www.apache.org/licenses/LICENSE-2.0
 
 mod cpu;
+mod cpupower;
 mod disk;
 mod ebpf;
 mod general;
+mod memcache;
+mod memory;
 mod network;
 mod perf;
 mod softnet;
 
 use self::cpu::Cpu;
+use self::cpupower::Cpupower;
 use self::disk::Disk;
 use self::ebpf::Ebpf;
 use self::general::General;
+use self::memcache::Memcache;
+use self::memory::Memory;
 use self::network::Network;
 use self::perf::Perf;
 use self::softnet::Softnet;
@@ -22,6 +28,7 @@ use crate::*;
 
 use std::io::Read;
 use std::net::{SocketAddr, ToSocketAddrs};
+use std::time::SystemTime;
 
 use clap::{App, Arg};
 use serde_derive::*;
@@ -29,23 +36,43 @@ use serde_derive::*;
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const NAME: &str = env!("CARGO_PKG_NAME");
 
+/// The settings of a config section which configures samplers. Each
+/// sampler's `Registration` returns its section, which is used to find its
+/// interval
+pub trait SamplerConfig {
+    /// the interval between samples in milliseconds, if set for the section
+    fn interval(&self) -> Option<usize> {
+        None
+    }
+}
+
 #[derive(Debug, Default, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Config {
     #[serde(default)]
     cpu: Cpu,
     #[serde(default)]
+    cpupower: Cpupower,
+    #[serde(default)]
     disk: Disk,
     #[serde(default)]
     ebpf: Ebpf,
     #[serde(default)]
     general: General,
     #[serde(default)]
+    memcache: Memcache,
+    #[serde(default)]
+    memory: Memory,
+    #[serde(default)]
     network: Network,
     #[serde(default)]
     perf: Perf,
     #[serde(default)]
     softnet: Softnet,
+    #[serde(skip)]
+    file: Option<String>,
+    #[serde(skip)]
+    list_samplers: bool,
 }
 
 impl Config {
@@ -68,17 +95,26 @@ impl Config {
                     .long("verbose")
                     .help("Increase verbosity by one level. Can be used more than once")
                     .multiple(true),
+            )
+            .arg(
+                Arg::with_name("list-samplers")
+                    .long("list-samplers")
+                    .help("List the built-in samplers and whether each is enabled"),
             );
 
         let matches = app.get_matches();
 
         let mut config = if let Some(file) = matches.value_of("config") {
-            Config::load_from_file(file)
+            let mut config = Config::load_from_file(file);
+            config.file = Some(file.to_string());
+            config
         } else {
             println!("NOTE: using builtin base configuration");
             Default::default()
         };
 
+        config.list_samplers = matches.is_present("list-samplers");
+
         config
             .general
             .set_logging(match matches.occurrences_of("verbose") {
@@ -90,6 +126,11 @@ impl Config {
         config
     }
 
+    /// should the samplers be listed instead of running the agent
+    pub fn list_samplers(&self) -> bool {
+        self.list_samplers
+    }
+
     /// get listen address
     pub fn listen(&self) -> Option<SocketAddr> {
         self.general
@@ -102,12 +143,6 @@ impl Config {
         self.general.logging()
     }
 
-    pub fn memcache(&self) -> Option<SocketAddr> {
-        self.general
-            .memcache()
-            .map(|v| v.to_socket_addrs().unwrap().next().unwrap())
-    }
-
     pub fn stats_log(&self) -> Option<String> {
         self.general.stats_log()
     }
@@ -116,6 +151,10 @@ impl Config {
         &self.cpu
     }
 
+    pub fn cpupower(&self) -> &Cpupower {
+        &self.cpupower
+    }
+
     pub fn disk(&self) -> &Disk {
         &self.disk
     }
@@ -124,6 +163,14 @@ impl Config {
         &self.general
     }
 
+    pub fn memcache(&self) -> &Memcache {
+        &self.memcache
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
     pub fn network(&self) -> &Network {
         &self.network
     }
@@ -141,13 +188,58 @@ impl Config {
         &self.ebpf
     }
 
+    /// get the last modification time of the config file
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.file
+            .as_ref()
+            .and_then(|file| std::fs::metadata(file).ok())
+            .and_then(|metadata| metadata.modified().ok())
+    }
+
+    /// re-read the config file, keeping the settings which were taken from
+    /// the command line. returns `None` if there is no config file or if the
+    /// new config could not be loaded
+    pub fn reload(&self) -> Option<Config> {
+        let file = self.file.as_ref()?;
+        match Config::parse_file(file) {
+            Ok(mut config) => {
+                config.file = Some(file.clone());
+                config.general.set_logging(self.logging());
+                Some(config)
+            }
+            Err(e) => {
+                error!("Failed to reload config: {}: {}", file, e);
+                None
+            }
+        }
+    }
+
+    /// returns true if the named section differs from the same section of
+    /// the `other` config
+    pub fn changed(&self, other: &Config, section: &str) -> bool {
+        if section == "general" {
+            return self.general != other.general;
+        }
+        match crate::samplers::registry()
+            .into_iter()
+            .find(|registration| registration.section == section)
+        {
+            Some(registration) => (registration.changed)(self, other),
+            None => true,
+        }
+    }
+
+    /// the interval, in milliseconds, between samples for the section,
+    /// which defaults to the interval of the `[general]` section
+    pub fn interval(&self, section: &dyn SamplerConfig) -> usize {
+        section
+            .interval()
+            .unwrap_or_else(|| self.general.interval())
+    }
+
     fn load_from_file(filename: &str) -> Config {
-        let mut file = std::fs::File::open(filename).expect("failed to open workload file");
-        let mut content = String::new();
-        file.read_to_string(&mut content).expect("failed to read");
-        let toml = toml::from_str(&content);
-        match toml {
-            Ok(toml) => toml,
+        match Config::parse_file(filename) {
+            Ok(config) => config,
             Err(e) => {
                 println!("Failed to parse TOML config: {}", filename);
                 println!("{}", e);
@@ -155,4 +247,83 @@ impl Config {
             }
         }
     }
+
+    fn parse_file(filename: &str) -> Result<Config, String> {
+        let mut file = std::fs::File::open(filename)
+            .map_err(|e| format!("failed to open config file: {}", e))?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)
+            .map_err(|e| format!("failed to read config file: {}", e))?;
+        Config::parse(&content)
+    }
+
+    fn parse(content: &str) -> Result<Config, String> {
+        let mut config: Config = toml::from_str(content).map_err(|e| e.to_string())?;
+        if let Some(endpoint) = config.general.take_memcache() {
+            // the logger may not be initialized while the config is loaded
+            println!("WARN: general.memcache is deprecated, use [memcache] endpoint instead");
+            if config.memcache.endpoint().is_none() {
+                config.memcache.set_endpoint(endpoint);
+            }
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// reject settings which would stall the samplers: a zero interval would
+    /// never advance to the next sample, zero `max_timeouts` would abandon
+    /// every sampler immediately, and a zero backoff would restart a failing
+    /// sampler in a tight loop
+    fn validate(&self) -> Result<(), String> {
+        if self.general.interval() == 0 {
+            return Err("general.interval must be greater than zero".to_string());
+        }
+        if self.general.max_timeouts() == 0 {
+            return Err("general.max_timeouts must be greater than zero".to_string());
+        }
+        if self.general.backoff() == 0 {
+            return Err("general.backoff must be greater than zero".to_string());
+        }
+        if self.general.max_backoff() < self.general.backoff() {
+            return Err("general.max_backoff must not be less than general.backoff".to_string());
+        }
+        for registration in crate::samplers::registry() {
+            if (registration.config)(self).interval() == Some(0) {
+                return Err(format!(
+                    "{}.interval must be greater than zero",
+                    registration.section
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reject_zero_interval() {
+        assert!(Config::parse("[general]\ninterval = 0\n").is_err());
+        assert!(Config::parse("[general]\nmax_timeouts = 0\n").is_err());
+        assert!(Config::parse("[general]\nbackoff = 0\n").is_err());
+        assert!(Config::parse("[general]\nbackoff = 2000\nmax_backoff = 1000\n").is_err());
+        assert!(Config::parse("[cpu]\ninterval = 0\n").is_err());
+        assert!(Config::parse("[cpu]\ninterval = 100\n").is_ok());
+    }
+
+    #[test]
+    fn deprecated_memcache() {
+        let config = Config::parse("[general]\nmemcache = \"localhost:11211\"\n").unwrap();
+        assert_eq!(
+            config.memcache().endpoint(),
+            Some("localhost:11211".to_string())
+        );
+        let config = Config::parse(
+            "[general]\nmemcache = \"old:11211\"\n[memcache]\nendpoint = \"new:11211\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.memcache().endpoint(), Some("new:11211".to_string()));
+    }
 }
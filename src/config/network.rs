@@ -7,23 +7,31 @@ use crate::config::*;
 use crate::samplers::network::interface::InterfaceStatistic;
 use crate::samplers::network::protocol::ProtocolStatistic;
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct Network {
     #[serde(default = "default_enabled")]
     enabled: bool,
+    interval: Option<usize>,
     #[serde(default = "default_interface_statistics")]
     interface_statistics: Vec<InterfaceStatistic>,
     #[serde(default = "default_protocol_statistics")]
     protocol_statistics: Vec<ProtocolStatistic>,
+    #[serde(default = "default_include")]
+    include: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
 }
 
 impl Default for Network {
     fn default() -> Network {
         Network {
             enabled: default_enabled(),
+            interval: None,
             interface_statistics: default_interface_statistics(),
             protocol_statistics: default_protocol_statistics(),
+            include: default_include(),
+            exclude: Vec::new(),
         }
     }
 }
@@ -63,6 +71,10 @@ fn default_protocol_statistics() -> Vec<ProtocolStatistic> {
     ]
 }
 
+fn default_include() -> Vec<String> {
+    vec!["^eth".to_string(), "^en".to_string(), "^em".to_string()]
+}
+
 impl Network {
     pub fn enabled(&self) -> bool {
         self.enabled
@@ -75,4 +87,21 @@ impl Network {
     pub fn protocol_statistics(&self) -> &[ProtocolStatistic] {
         &self.protocol_statistics
     }
+
+    /// patterns for the names of interfaces to monitor. all interfaces are
+    /// monitored if this is empty
+    pub fn include(&self) -> &[String] {
+        &self.include
+    }
+
+    /// patterns for the names of interfaces to ignore, even if included
+    pub fn exclude(&self) -> &[String] {
+        &self.exclude
+    }
+}
+
+impl SamplerConfig for Network {
+    fn interval(&self) -> Option<usize> {
+        self.interval
+    }
 }
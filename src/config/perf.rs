@@ -5,11 +5,12 @@
 use crate::config::*;
 use crate::samplers::perf::PerfStatistic;
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct Perf {
     #[serde(default = "default_enabled")]
     enabled: bool,
+    interval: Option<usize>,
     #[serde(default = "default_statistics")]
     statistics: Vec<PerfStatistic>,
 }
@@ -18,6 +19,7 @@ impl Default for Perf {
     fn default() -> Perf {
         Perf {
             enabled: default_enabled(),
+            interval: None,
             statistics: default_statistics(),
         }
     }
@@ -61,3 +63,9 @@ impl Perf {
         &self.statistics
     }
 }
+
+impl SamplerConfig for Perf {
+    fn interval(&self) -> Option<usize> {
+        self.interval
+    }
+}
@@ -4,17 +4,19 @@
 
 use crate::config::*;
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct Softnet {
     #[serde(default = "default_enabled")]
     enabled: bool,
+    interval: Option<usize>,
 }
 
 impl Default for Softnet {
     fn default() -> Softnet {
         Softnet {
             enabled: default_enabled(),
+            interval: None,
         }
     }
 }
@@ -28,3 +30,9 @@ impl Softnet {
         self.enabled
     }
 }
+
+impl SamplerConfig for Softnet {
+    fn interval(&self) -> Option<usize> {
+        self.interval
+    }
+}
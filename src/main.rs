@@ -5,34 +5,39 @@
 mod common;
 mod config;
 mod samplers;
+mod scheduler;
 mod stats;
 
 use crate::common::*;
 use crate::config::Config;
-use crate::samplers::*;
+use crate::scheduler::Scheduler;
 
 use logger::*;
 use metrics::{Metrics, Reading};
 
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::{Duration, Instant};
-
-struct Stats {
-    sequential_timeouts: usize,
-}
-
-impl Default for Stats {
-    fn default() -> Self {
-        Self {
-            sequential_timeouts: 0,
-        }
-    }
-}
+use std::time::Duration;
 
 fn main() {
     // get config
-    let config = Config::new();
+    let mut config = Arc::new(Config::new());
+
+    if config.list_samplers() {
+        for registration in samplers::registry() {
+            let status = if (registration.enabled)(&config) {
+                "enabled"
+            } else {
+                "disabled"
+            };
+            println!(
+                "{:<20} [{}] {}",
+                registration.name, registration.section, status
+            );
+        }
+        return;
+    }
 
     // initialize logging
     Logger::new()
@@ -49,68 +54,37 @@ fn main() {
         env!("VERGEN_BUILD_TIMESTAMP"),
         env!("VERGEN_TARGET_TRIPLE")
     );
-    debug!("host cores: {}", hardware_threads().unwrap_or(1));
+    debug!(
+        "host cores: {}",
+        hardware_threads(config.general().sys_root()).unwrap_or(1)
+    );
 
     let metrics = Metrics::new();
     let recorder = metrics.recorder();
-    let mut samplers = Vec::<(Box<dyn Sampler>, Stats)>::new();
+    let mut scheduler = Scheduler::new(config.clone(), metrics.recorder());
 
-    // register samplers
-    if config.memcache().is_some() {
-        info!("memcache proxy mode");
-        if let Ok(Some(s)) = samplers::Memcache::new(&config, &recorder) {
-            samplers.push((s, Stats::default()));
-        }
-    } else {
-        if let Ok(Some(s)) = samplers::Cpu::new(&config, &recorder) {
-            samplers.push((s, Stats::default()));
-        }
-        if let Ok(Some(s)) = samplers::Disk::new(&config, &recorder) {
-            samplers.push((s, Stats::default()));
-        }
-        if let Ok(Some(s)) = samplers::Rezolus::new(&config, &recorder) {
-            samplers.push((s, Stats::default()));
-        }
-        if let Ok(Some(s)) = samplers::Network::new(&config, &recorder) {
-            samplers.push((s, Stats::default()));
-        }
-        #[cfg(feature = "ebpf")]
-        {
-            if config.ebpf().block() {
-                if let Ok(Some(s)) = ebpf::Block::new(&config, &recorder) {
-                    samplers.push((s, Stats::default()));
-                }
-            }
-            if config.ebpf().ext4() {
-                if let Ok(Some(s)) = ebpf::Ext4::new(&config, &recorder) {
-                    samplers.push((s, Stats::default()));
-                }
-            }
-            if config.ebpf().scheduler() {
-                if let Ok(Some(s)) = ebpf::Scheduler::new(&config, &recorder) {
-                    samplers.push((s, Stats::default()));
-                }
-            }
-            if config.ebpf().xfs() {
-                if let Ok(Some(s)) = ebpf::Xfs::new(&config, &recorder) {
-                    samplers.push((s, Stats::default()));
-                }
-            }
-        }
-        #[cfg(feature = "perf")]
-        {
-            if let Ok(Some(s)) = samplers::Perf::new(&config, &recorder) {
-                samplers.push((s, Stats::default()));
-            }
-        }
-        if let Ok(Some(s)) = samplers::Softnet::new(&config, &recorder) {
-            samplers.push((s, Stats::default()));
+    // add each sampler to the scheduler
+    for registration in samplers::registry() {
+        scheduler.add(registration);
+    }
+
+    // reload the config on SIGHUP or when the config file changes
+    let reload = Arc::new(AtomicBool::new(false));
+    if let Err(e) = signal_hook::flag::register(signal_hook::SIGHUP, reload.clone()) {
+        error!("failed to register SIGHUP handler: {}", e);
+    }
+    // shutdown gracefully on SIGTERM or SIGINT
+    let shutdown = Arc::new(AtomicBool::new(false));
+    for signal in &[signal_hook::SIGTERM, signal_hook::SIGINT] {
+        if let Err(e) = signal_hook::flag::register(*signal, shutdown.clone()) {
+            error!("failed to register handler for signal {}: {}", signal, e);
         }
     }
 
+    let mut modified = config.modified();
+    let mut modified_check = time::precise_time_ns() + SECOND;
+
     let time = time::precise_time_ns();
-    // calculate interval in nanoseconds between samples
-    let sample_interval = config.general().interval() as u64 * MILLISECOND;
 
     // snapshot at 2Hz to prevent stale samples at 1Hz external sampling
     let snapshot_interval = SECOND / 2;
@@ -124,11 +98,11 @@ fn main() {
 
     let readings = Arc::new(Mutex::new(Vec::<Reading>::new()));
 
-    let count_suffix = if config.memcache().is_none() {
+    let count_suffix = if !config.general().passthrough() {
         // running in Vex mode and we need a count suffix (all values must be leaf nodes)
         Some("count")
     } else {
-        // running in memcache mode and must NOT have a count suffix
+        // running in passthrough mode and must NOT have a count suffix
         // resulting in passthrough of original metric name
         None
     };
@@ -137,89 +111,91 @@ fn main() {
         fatal!("no listen address");
     });
     let mut stats_http = stats::Http::new(listen, metrics.recorder(), count_suffix);
-    let _ = thread::Builder::new()
-        .name("http".to_string())
-        .spawn(move || loop {
-            stats_http.run();
-        });
+    let http = {
+        let shutdown = shutdown.clone();
+        thread::Builder::new()
+            .name("http".to_string())
+            .spawn(move || {
+                while !shutdown.load(Ordering::Relaxed) {
+                    stats_http.run();
+                }
+            })
+            .ok()
+    };
 
-    if let Some(stats_log) = config.stats_log() {
+    let logger = config.stats_log().and_then(|stats_log| {
         let mut stats_logger = stats::StatsLog::new(&stats_log, metrics.recorder(), count_suffix);
-        let _ = thread::Builder::new()
+        let shutdown = shutdown.clone();
+        thread::Builder::new()
             .name("logger".to_string())
-            .spawn(move || loop {
-                stats_logger.run();
-            });
-    }
+            .spawn(move || stats_logger.run(&shutdown))
+            .ok()
+    });
 
     // let mut stats_stdout = stats::StatsLog::new(&recorder);
 
-    let mut first_run = true;
-
-    loop {
-        debug!("Sampling...");
-        let start = time::precise_time_ns();
-
-        // sample each sampler
-        let mut samplers_temp = Vec::new();
-        for (mut sampler, mut stats) in samplers.drain(..) {
-            let start = Instant::now();
-            let result = sampler.sample();
-            let stop = Instant::now();
-            let runtime = stop - start;
-
-            match result {
-                Ok(_) => {
-                    if !first_run && runtime.subsec_millis() as usize > config.general().timeout() {
-                        stats.sequential_timeouts += 1;
-                        if stats.sequential_timeouts >= config.general().max_timeouts() {
-                            warn!(
-                                "Sampler {} took over {} ms {} times sequentially. Failing the sampler",
-                                sampler.name(),
-                                config.general().timeout(),
-                                stats.sequential_timeouts
-                            );
-                            sampler.deregister();
-                        } else {
-                            stats.sequential_timeouts = 0;
-                            samplers_temp.push((sampler, stats));
-                        }
-                    } else {
-                        samplers_temp.push((sampler, stats));
-                    }
-                }
-                Err(_) => {
-                    warn!(
-                        "Sampler {} returned a fatal error. Failing the sampler",
-                        sampler.name()
-                    );
-                    sampler.deregister();
+    // samplers run on their own workers, this thread supervises them and
+    // manages the snapshots
+    while !shutdown.load(Ordering::Relaxed) {
+        let now = time::precise_time_ns();
+
+        // check if the config needs to be reloaded
+        let mut changed = reload.swap(false, Ordering::Relaxed);
+        if now >= modified_check {
+            let current = config.modified();
+            if current != modified {
+                modified = current;
+                changed = true;
+            }
+            modified_check = now + SECOND;
+        }
+        if changed {
+            if let Some(new) = config.reload() {
+                info!("reloading config");
+                if new.listen() != config.listen()
+                    || new.stats_log() != config.stats_log()
+                    || new.general().passthrough() != config.general().passthrough()
+                {
+                    warn!("changes to listen, stats_log, or passthrough require a restart");
                 }
+                config = Arc::new(new);
+                scheduler.reload(config.clone());
             }
         }
-        samplers = samplers_temp;
-        debug!("sampling complete");
+
+        scheduler.supervise();
 
         // take a snapshot if necessary
-        if time >= snapshot_time {
+        if now >= snapshot_time {
             let current_readings = recorder.readings();
             let mut readings = readings.lock().unwrap();
             *readings = current_readings;
             snapshot_time += snapshot_interval;
 
             // clear any latched histograms and min/max if necessary
-            if time >= latch_time {
+            if now >= latch_time {
                 recorder.latch();
                 latch_time += latch_interval;
             }
         }
 
-        first_run = false;
+        thread::sleep(Duration::from_nanos(POLL_DELAY));
+    }
+
+    info!("shutting down");
 
-        let stop = time::precise_time_ns();
+    // flush the stats log while the samplers' metrics are still registered
+    if let Some(logger) = logger {
+        let _ = logger.join();
+    }
 
-        let sleep = sample_interval - (stop - start);
-        debug!("sleep for: {} ns", sleep);
-        thread::sleep(Duration::from_nanos(sleep));
+    // stop the http listener
+    if let Some(http) = http {
+        let _ = http.join();
     }
+
+    // deregister the samplers, which detaches any probes
+    scheduler.shutdown(SHUTDOWN_TIMEOUT);
+
+    info!("shutdown complete");
 }
@@ -4,10 +4,11 @@
 
 use crate::common::*;
 use crate::config::Config;
-use crate::samplers::Sampler;
 use crate::samplers::Statistic;
-use crate::stats::record_counter;
-use crate::stats::register_counter;
+use crate::samplers::{construct, Registration, Sampler, SamplerError};
+use crate::stats::{
+    delete_channel, record_counter, record_gauge, register_counter, register_gauge, Labeled, Unit,
+};
 use failure::Error;
 
 use logger::*;
@@ -15,11 +16,21 @@ use metrics::*;
 use serde_derive::*;
 use time;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
 
-const PROC_STAT: &str = "/proc/stat";
+mod system;
+mod topology;
+
+use self::system::parse_system_line;
+pub use self::system::SystemStatistic;
+use self::topology::Topology;
+
+const PROC_STAT: &str = "stat";
 
 // reported percentiles
 pub const PERCENTILES: &[Percentile] = &[
@@ -34,11 +45,74 @@ pub const PERCENTILES: &[Percentile] = &[
     Percentile::Maximum,
 ];
 
-pub struct Cpu<'a> {
-    config: &'a Config,
+pub const REGISTRATION: Registration = Registration {
+    name: "cpu",
+    section: "cpu",
+    config: |config| config.cpu(),
+    changed: |old, new| old.cpu() != new.cpu(),
+    enabled: |config| config.cpu().enabled(),
+    new: construct::<Cpu>,
+};
+
+pub struct Cpu {
+    config: Arc<Config>,
     nanos_per_tick: u64,
     initialized: bool,
-    recorder: &'a Recorder<AtomicU32>,
+    recorder: Recorder<AtomicU32>,
+    /// the groups of CPUs for which channels are registered
+    registered: HashSet<Group>,
+    topology: Topology,
+    /// the number of hardware threads, read along with the topology
+    cores: u64,
+    /// the busy and total ticks of each group at the last sample
+    previous: HashMap<Group, (u64, u64)>,
+    /// the IRQs for which per-IRQ interrupt channels are registered
+    registered_irqs: HashSet<usize>,
+}
+
+/// A group of CPUs which is reported together, identified by the label of its
+/// channels: a single CPU, socket or NUMA node. `None` is all CPUs
+type Group = Option<(&'static str, u64)>;
+
+/// Labels the statistic with the `Group`, unless it is all CPUs
+fn labeled<T: Statistic>(statistic: T, group: &Group) -> Labeled<T> {
+    match group {
+        Some((key, id)) => statistic.per(*key, id),
+        None => Labeled::new(statistic),
+    }
+}
+
+/// The percentage of time where the CPUs were busy, derived from the change
+/// in the `CpuStatistic`s between samples
+#[derive(Clone, Copy, Debug)]
+pub struct Utilization;
+
+impl fmt::Display for Utilization {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cpu/utilization")
+    }
+}
+
+impl Statistic for Utilization {
+    fn description(&self) -> &str {
+        "percentage of time where the CPU was not idle"
+    }
+}
+
+/// Returns the busy and total ticks. Guest time is already counted as user
+/// time, so is excluded from the total. Time waiting on IO is idle
+fn ticks(statistics: &HashMap<CpuStatistic, u64>) -> (u64, u64) {
+    let total: u64 = statistics
+        .iter()
+        .filter(|(statistic, _)| match statistic {
+            CpuStatistic::Guest | CpuStatistic::GuestNice => false,
+            _ => true,
+        })
+        .map(|(_, value)| value)
+        .sum();
+    let idle = *statistics.get(&CpuStatistic::Idle).unwrap_or(&0)
+        + *statistics.get(&CpuStatistic::Iowait).unwrap_or(&0);
+    (total - idle, total)
 }
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Hash)]
@@ -48,6 +122,7 @@ pub enum CpuStatistic {
     Nice,
     System,
     Idle,
+    Iowait,
     Irq,
     Softirq,
     Steal,
@@ -62,6 +137,7 @@ impl std::fmt::Display for CpuStatistic {
             CpuStatistic::Nice => write!(f, "cpu/nice"),
             CpuStatistic::System => write!(f, "cpu/system"),
             CpuStatistic::Idle => write!(f, "cpu/idle"),
+            CpuStatistic::Iowait => write!(f, "cpu/iowait"),
             CpuStatistic::Irq => write!(f, "cpu/irq"),
             CpuStatistic::Softirq => write!(f, "cpu/softirq"),
             CpuStatistic::Steal => write!(f, "cpu/steal"),
@@ -71,62 +147,239 @@ impl std::fmt::Display for CpuStatistic {
     }
 }
 
-impl Statistic for CpuStatistic {}
+impl Statistic for CpuStatistic {
+    fn description(&self) -> &str {
+        match self {
+            CpuStatistic::User => "time, in nanoseconds, spent in user-space",
+            CpuStatistic::Nice => "time, in nanoseconds, spent on lower-priority tasks",
+            CpuStatistic::System => "time, in nanoseconds, spent in kernel-space",
+            CpuStatistic::Idle => "time, in nanoseconds, where nothing is running",
+            CpuStatistic::Iowait => "time, in nanoseconds, idle while waiting on IO",
+            CpuStatistic::Irq => "time, in nanoseconds, handling interrupts",
+            CpuStatistic::Softirq => "time, in nanoseconds, handling soft interrupts",
+            CpuStatistic::Steal => "time, in nanoseconds, stolen by the hypervisor",
+            CpuStatistic::Guest => "time, in nanoseconds, running a guest VM",
+            CpuStatistic::GuestNice => "time, in nanoseconds, running a low-priority guest VM",
+        }
+    }
+
+    fn unit(&self) -> Option<Unit> {
+        Some(Unit::Nanoseconds)
+    }
+}
 
 struct ProcStat {
     cpu_total: HashMap<CpuStatistic, u64>,
+    /// the statistics of each CPU, by its id
+    cpu: HashMap<usize, HashMap<CpuStatistic, u64>>,
+    system: HashMap<SystemStatistic, u64>,
+    /// the number of interrupts serviced for each IRQ
+    irqs: HashMap<usize, u64>,
 }
 
-fn read_proc_stat() -> ProcStat {
-    let file = File::open(PROC_STAT)
-        .map_err(|e| debug!("could not read {}: {}", PROC_STAT, e))
-        .unwrap();
+fn read_proc_stat(proc_root: &Path) -> Result<ProcStat, SamplerError> {
+    let path = proc_root.join(PROC_STAT);
+    let file = File::open(&path).map_err(|e| {
+        SamplerError::Transient(format!("could not open {}: {}", path.display(), e))
+    })?;
     let mut file = BufReader::new(file);
     parse_proc_stat(&mut file)
 }
 
-fn parse_proc_stat<T: BufRead>(reader: &mut T) -> ProcStat {
+fn parse_proc_stat<T: BufRead>(reader: &mut T) -> Result<ProcStat, SamplerError> {
     let mut ret = ProcStat {
         cpu_total: HashMap::new(),
+        cpu: HashMap::new(),
+        system: HashMap::new(),
+        irqs: HashMap::new(),
     };
     for line in reader.lines() {
-        let line = line.unwrap();
+        let line =
+            line.map_err(|e| SamplerError::Transient(format!("could not read stat: {}", e)))?;
         let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts[0] == "cpu" && parts.len() == 11 {
-            ret.cpu_total
-                .insert(CpuStatistic::User, parts[1].parse().unwrap_or(0));
-            ret.cpu_total
-                .insert(CpuStatistic::Nice, parts[2].parse().unwrap_or(0));
-            ret.cpu_total
-                .insert(CpuStatistic::System, parts[3].parse().unwrap_or(0));
-            ret.cpu_total
-                .insert(CpuStatistic::Idle, parts[4].parse().unwrap_or(0));
-            ret.cpu_total
-                .insert(CpuStatistic::Irq, parts[6].parse().unwrap_or(0));
-            ret.cpu_total
-                .insert(CpuStatistic::Softirq, parts[7].parse().unwrap_or(0));
-            ret.cpu_total
-                .insert(CpuStatistic::Steal, parts[8].parse().unwrap_or(0));
-            ret.cpu_total
-                .insert(CpuStatistic::Guest, parts[9].parse().unwrap_or(0));
-            ret.cpu_total
-                .insert(CpuStatistic::GuestNice, parts[10].parse().unwrap_or(0));
+        match parts.first() {
+            Some(name) if name.starts_with("cpu") => {
+                if *name == "cpu" {
+                    ret.cpu_total = parse_cpu_line(&parts);
+                } else if let Ok(id) = name[3..].parse() {
+                    ret.cpu.insert(id, parse_cpu_line(&parts));
+                }
+            }
+            Some(_) => parse_system_line(&parts, &mut ret.system, &mut ret.irqs),
+            None => {}
+        }
+    }
+    Ok(ret)
+}
+
+/// The statistics in the order of their columns on a `cpu` line. Older
+/// kernels report fewer columns: steal was added in 2.6.11, guest in 2.6.24
+/// and guest_nice in 2.6.33. Columns added by newer kernels are ignored
+const CPU_COLUMNS: [CpuStatistic; 10] = [
+    CpuStatistic::User,
+    CpuStatistic::Nice,
+    CpuStatistic::System,
+    CpuStatistic::Idle,
+    CpuStatistic::Iowait,
+    CpuStatistic::Irq,
+    CpuStatistic::Softirq,
+    CpuStatistic::Steal,
+    CpuStatistic::Guest,
+    CpuStatistic::GuestNice,
+];
+
+/// Maps the fields of a `cpu` line, including its name, to the statistics.
+/// Statistics which the kernel does not report are absent
+fn parse_cpu_line(parts: &[&str]) -> HashMap<CpuStatistic, u64> {
+    let mut ret = HashMap::new();
+    for (statistic, value) in CPU_COLUMNS.iter().zip(parts.iter().skip(1)) {
+        if let Ok(value) = value.parse() {
+            ret.insert(statistic.clone(), value);
         }
     }
     ret
 }
 
-impl<'a> Sampler<'a> for Cpu<'a> {
-    fn new(
-        config: &'a Config,
-        recorder: &'a Recorder<AtomicU32>,
-    ) -> Result<Option<Box<Self>>, Error> {
+impl Cpu {
+    /// Groups the statistics of each CPU by the configured CPU, socket and
+    /// node breakdowns, along with the total across all CPUs
+    fn groups(&self, data: ProcStat) -> HashMap<Group, HashMap<CpuStatistic, u64>> {
+        let config = self.config.cpu();
+        let mut groups = HashMap::new();
+        for (id, statistics) in data.cpu {
+            let mut keys = Vec::new();
+            if config.per_cpu() {
+                keys.push(Some(("cpu", id as u64)));
+            }
+            if config.per_socket() {
+                if let Some(socket) = self.topology.socket(id) {
+                    keys.push(Some(("socket", socket)));
+                }
+            }
+            if config.per_node() {
+                if let Some(node) = self.topology.node(id) {
+                    keys.push(Some(("node", node)));
+                }
+            }
+            for key in keys {
+                let group: &mut HashMap<CpuStatistic, u64> = groups.entry(key).or_default();
+                for (statistic, value) in &statistics {
+                    *group.entry(statistic.clone()).or_insert(0) += value;
+                }
+            }
+        }
+        groups.insert(None, data.cpu_total);
+        groups
+    }
+
+    /// register the channels for a `Group`. `cores` is the number of CPUs in
+    /// the group, which bounds the rate of each statistic
+    fn register_group(&self, group: &Group, cores: u64) {
+        for statistic in self.config.cpu().statistics() {
+            register_counter(
+                &self.recorder,
+                labeled(statistic, group),
+                2 * cores * SECOND,
+                3,
+                self.config.general().window(),
+                PERCENTILES,
+            );
+        }
+        register_gauge(
+            &self.recorder,
+            labeled(Utilization, group),
+            100,
+            3,
+            self.config.general().window(),
+            PERCENTILES,
+        );
+    }
+
+    /// register the channels of the statistics for the whole system
+    fn register_system(&self) {
+        for statistic in self.config.cpu().system_statistics() {
+            if statistic.is_gauge() {
+                register_gauge(
+                    &self.recorder,
+                    &statistic,
+                    statistic.max(),
+                    3,
+                    self.config.general().window(),
+                    PERCENTILES,
+                );
+            } else {
+                register_counter(
+                    &self.recorder,
+                    &statistic,
+                    statistic.max(),
+                    3,
+                    self.config.general().window(),
+                    PERCENTILES,
+                );
+            }
+        }
+    }
+
+    fn record_system(&mut self, time: u64, data: &ProcStat) {
+        let statistics = self.config.cpu().system_statistics();
+        for statistic in &statistics {
+            if let Some(value) = data.system.get(statistic) {
+                if statistic.is_gauge() {
+                    record_gauge(&self.recorder, statistic, time, *value);
+                } else {
+                    record_counter(&self.recorder, statistic, time, *value);
+                }
+            }
+        }
+        if !self.config.cpu().per_irq() || !statistics.contains(&SystemStatistic::Interrupts) {
+            return;
+        }
+        for (irq, value) in &data.irqs {
+            // most IRQs are unused, so are registered once they are serviced
+            if !self.registered_irqs.contains(irq) {
+                if *value == 0 {
+                    continue;
+                }
+                register_counter(
+                    &self.recorder,
+                    SystemStatistic::Interrupts.per("irq", irq),
+                    SystemStatistic::Interrupts.max(),
+                    3,
+                    self.config.general().window(),
+                    PERCENTILES,
+                );
+                self.registered_irqs.insert(*irq);
+            }
+            record_counter(
+                &self.recorder,
+                SystemStatistic::Interrupts.per("irq", irq),
+                time,
+                *value,
+            );
+        }
+    }
+
+    fn deregister_group(&self, group: &Group) {
+        for statistic in self.config.cpu().statistics() {
+            delete_channel(&self.recorder, labeled(statistic, group).to_string());
+        }
+        delete_channel(&self.recorder, labeled(Utilization, group).to_string());
+    }
+}
+
+impl Sampler for Cpu {
+    fn new(config: Arc<Config>, recorder: Recorder<AtomicU32>) -> Result<Option<Box<Self>>, Error> {
         if config.cpu().enabled() {
             Ok(Some(Box::new(Cpu {
                 config,
                 nanos_per_tick: crate::common::nanos_per_tick(),
                 initialized: false,
                 recorder,
+                registered: HashSet::new(),
+                topology: Topology::default(),
+                cores: 1,
+                previous: HashMap::new(),
+                registered_irqs: HashSet::new(),
             })))
         } else {
             Ok(None)
@@ -136,37 +389,75 @@ impl<'a> Sampler<'a> for Cpu<'a> {
         "cpu".to_string()
     }
 
-    fn sample(&mut self) -> Result<(), ()> {
+    fn sample(&mut self) -> Result<(), SamplerError> {
         trace!("sample {}", self.name());
-        let data = read_proc_stat();
+        let data = read_proc_stat(self.config.general().proc_root())?;
         let time = time::precise_time_ns();
         if !self.initialized {
             self.register();
         }
-        for statistic in self.config.cpu().statistics() {
-            let raw = *data.cpu_total.get(&statistic).unwrap_or(&0);
-            let value = raw * self.nanos_per_tick;
-            record_counter(self.recorder, statistic, time, value);
+        self.record_system(time, &data);
+        let groups = self.groups(data);
+        for (group, statistics) in &groups {
+            if !self.registered.contains(group) {
+                // groups are registered on sight, so CPUs brought online are
+                // picked up. sockets and nodes are bounded by all the CPUs
+                let group_cores = match group {
+                    Some(("cpu", _)) => 1,
+                    _ => self.cores,
+                };
+                self.register_group(group, group_cores);
+                self.registered.insert(*group);
+            }
+            for statistic in self.config.cpu().statistics() {
+                if let Some(raw) = statistics.get(&statistic) {
+                    let value = raw * self.nanos_per_tick;
+                    record_counter(&self.recorder, labeled(statistic, group), time, value);
+                }
+            }
+            let (busy, total) = ticks(statistics);
+            if let Some((previous_busy, previous_total)) = self.previous.get(group) {
+                let total = total.saturating_sub(*previous_total);
+                if total > 0 {
+                    let busy = busy.saturating_sub(*previous_busy);
+                    let utilization = 100 * busy / total;
+                    record_gauge(
+                        &self.recorder,
+                        labeled(Utilization, group),
+                        time,
+                        utilization,
+                    );
+                }
+            }
         }
+        // remove the channels of groups which are gone, such as CPUs taken
+        // offline, rather than leaving them with stale values
+        let vanished: Vec<Group> = self
+            .registered
+            .iter()
+            .filter(|group| !groups.contains_key(*group))
+            .cloned()
+            .collect();
+        for group in &vanished {
+            self.deregister_group(group);
+            self.registered.remove(group);
+        }
+        self.previous = groups
+            .iter()
+            .map(|(group, statistics)| (*group, ticks(statistics)))
+            .collect();
         Ok(())
     }
 
     fn register(&mut self) {
         trace!("register {}", self.name());
         if !self.initialized {
-            let cores = crate::common::hardware_threads().unwrap_or(1);
-
-            for statistic in self.config.cpu().statistics() {
-                register_counter(
-                    self.recorder,
-                    statistic,
-                    2 * cores * SECOND,
-                    3,
-                    self.config.general().window(),
-                    PERCENTILES,
-                );
-            }
-
+            self.cores =
+                crate::common::hardware_threads(self.config.general().sys_root()).unwrap_or(1);
+            self.topology = Topology::new(self.config.general().sys_root());
+            self.register_group(&None, self.cores);
+            self.registered.insert(None);
+            self.register_system();
             self.initialized = true;
         }
     }
@@ -174,9 +465,21 @@ impl<'a> Sampler<'a> for Cpu<'a> {
     fn deregister(&mut self) {
         trace!("deregister {}", self.name());
         if self.initialized {
-            for statistic in self.config.cpu().statistics() {
-                self.recorder.delete_channel(statistic.to_string());
+            for group in &self.registered {
+                self.deregister_group(group);
+            }
+            self.registered.clear();
+            self.previous.clear();
+            for statistic in self.config.cpu().system_statistics() {
+                delete_channel(&self.recorder, statistic.to_string());
+            }
+            for irq in &self.registered_irqs {
+                delete_channel(
+                    &self.recorder,
+                    SystemStatistic::Interrupts.per("irq", irq).to_string(),
+                );
             }
+            self.registered_irqs.clear();
             self.initialized = false;
         }
     }
@@ -188,11 +491,11 @@ mod tests {
 
     #[test]
     fn test_parse_proc_stat() {
-        let file = File::open(&format!("tests/data{}", PROC_STAT))
+        let file = File::open(Path::new("tests/data/proc").join(PROC_STAT))
             .map_err(|e| panic!("could not read file: {}", e))
             .unwrap();
         let mut file = BufReader::new(file);
-        let data = parse_proc_stat(&mut file);
+        let data = parse_proc_stat(&mut file).unwrap();
         assert_eq!(
             *data.cpu_total.get(&CpuStatistic::User).unwrap_or(&0),
             370627
@@ -206,6 +509,10 @@ mod tests {
             *data.cpu_total.get(&CpuStatistic::Idle).unwrap_or(&0),
             8020800
         );
+        assert_eq!(
+            *data.cpu_total.get(&CpuStatistic::Iowait).unwrap_or(&0),
+            4570
+        );
         assert_eq!(*data.cpu_total.get(&CpuStatistic::Irq).unwrap_or(&0), 0);
         assert_eq!(
             *data.cpu_total.get(&CpuStatistic::Softirq).unwrap_or(&0),
@@ -218,4 +525,93 @@ mod tests {
             0
         );
     }
+
+    /// parse a fixture of `/proc/stat` as reported by another kernel version
+    fn parse_fixture(name: &str) -> ProcStat {
+        let file = File::open(Path::new("tests/data/proc").join(name))
+            .map_err(|e| panic!("could not read file: {}", e))
+            .unwrap();
+        parse_proc_stat(&mut BufReader::new(file)).unwrap()
+    }
+
+    #[test]
+    fn test_parse_proc_stat_kernel_versions() {
+        // 2.6.9 has no steal, guest or guest_nice columns
+        let data = parse_fixture("stat-2.6.9");
+        assert_eq!(data.cpu_total.len(), 7);
+        assert_eq!(data.cpu_total[&CpuStatistic::Iowait], 40);
+        assert_eq!(data.cpu_total[&CpuStatistic::Softirq], 7);
+        assert_eq!(data.cpu_total.get(&CpuStatistic::Steal), None);
+        assert_eq!(data.cpu.len(), 1);
+        assert_eq!(data.system[&SystemStatistic::ContextSwitches], 1200);
+
+        // 2.6.18 adds steal
+        let data = parse_fixture("stat-2.6.18");
+        assert_eq!(data.cpu_total.len(), 8);
+        assert_eq!(data.cpu_total[&CpuStatistic::Steal], 5);
+        assert_eq!(data.cpu[&1][&CpuStatistic::Steal], 3);
+        assert_eq!(data.cpu_total.get(&CpuStatistic::Guest), None);
+
+        // 2.6.30 adds guest
+        let data = parse_fixture("stat-2.6.30");
+        assert_eq!(data.cpu_total.len(), 9);
+        assert_eq!(data.cpu_total[&CpuStatistic::Guest], 30);
+        assert_eq!(data.cpu_total.get(&CpuStatistic::GuestNice), None);
+        assert_eq!(data.system[&SystemStatistic::SoftirqNetTx], 0);
+        assert_eq!(data.system[&SystemStatistic::SoftirqNetRx], 400);
+
+        // columns beyond guest_nice are ignored
+        let data = parse_fixture("stat-extra");
+        assert_eq!(data.cpu_total.len(), 10);
+        assert_eq!(data.cpu_total[&CpuStatistic::GuestNice], 4);
+        assert_eq!(data.cpu[&0][&CpuStatistic::User], 2000);
+    }
+
+    #[test]
+    fn test_parse_proc_stat_blank_lines() {
+        let data = parse_proc_stat(&mut "\ncpu  1 2 3 4\n\n   \nctxt 5\n".as_bytes()).unwrap();
+        assert_eq!(data.cpu_total[&CpuStatistic::Idle], 4);
+        assert_eq!(data.system[&SystemStatistic::ContextSwitches], 5);
+    }
+
+    #[test]
+    fn test_read_proc_stat_from_root() {
+        let data = read_proc_stat(Path::new("tests/data/proc")).unwrap();
+        assert_eq!(
+            *data.cpu_total.get(&CpuStatistic::User).unwrap_or(&0),
+            370627
+        );
+    }
+
+    #[test]
+    fn test_parse_proc_stat_per_cpu() {
+        let data = read_proc_stat(Path::new("tests/data/proc")).unwrap();
+        assert_eq!(data.cpu.len(), 4);
+        assert_eq!(*data.cpu[&0].get(&CpuStatistic::User).unwrap_or(&0), 95024);
+        assert_eq!(
+            *data.cpu[&3].get(&CpuStatistic::Idle).unwrap_or(&0),
+            2010295
+        );
+    }
+
+    #[test]
+    fn test_parse_proc_stat_system() {
+        let data = read_proc_stat(Path::new("tests/data/proc")).unwrap();
+        assert_eq!(data.system[&SystemStatistic::ContextSwitches], 4744363);
+        assert_eq!(data.system[&SystemStatistic::Interrupts], 4268467);
+        assert_eq!(data.system[&SystemStatistic::Forks], 84681);
+        assert_eq!(data.system[&SystemStatistic::ProcessesRunning], 2);
+        assert_eq!(data.system[&SystemStatistic::BootTime], 1556213902);
+        assert_eq!(data.system[&SystemStatistic::SoftirqNetRx], 1116067);
+        assert_eq!(data.system[&SystemStatistic::SoftirqRcu], 1678853);
+        assert_eq!(data.irqs[&18], 2496930);
+    }
+
+    #[test]
+    fn test_ticks() {
+        let data = read_proc_stat(Path::new("tests/data/proc")).unwrap();
+        let (busy, total) = ticks(&data.cpu[&0]);
+        assert_eq!(total, 95024 + 16457 + 1990725 + 1406 + 1503);
+        assert_eq!(busy, 95024 + 16457 + 1503);
+    }
 }
@@ -0,0 +1,197 @@
+// Copyright 2019 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use crate::common::{BILLION, MILLION, TRILLION};
+use crate::samplers::Statistic;
+use crate::stats::Unit;
+
+use serde_derive::*;
+
+use std::collections::HashMap;
+
+/// Counters for the whole system which are reported in `/proc/stat` alongside
+/// the time spent by the CPUs
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Hash)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub enum SystemStatistic {
+    ContextSwitches,
+    Interrupts,
+    Softirqs,
+    SoftirqHi,
+    SoftirqTimer,
+    SoftirqNetTx,
+    SoftirqNetRx,
+    SoftirqBlock,
+    SoftirqIrqPoll,
+    SoftirqTasklet,
+    SoftirqSched,
+    SoftirqHrtimer,
+    SoftirqRcu,
+    Forks,
+    ProcessesRunning,
+    ProcessesBlocked,
+    BootTime,
+}
+
+/// The softirq types, in the order of their columns on the `softirq` line
+/// after the total
+const SOFTIRQS: [SystemStatistic; 10] = [
+    SystemStatistic::SoftirqHi,
+    SystemStatistic::SoftirqTimer,
+    SystemStatistic::SoftirqNetTx,
+    SystemStatistic::SoftirqNetRx,
+    SystemStatistic::SoftirqBlock,
+    SystemStatistic::SoftirqIrqPoll,
+    SystemStatistic::SoftirqTasklet,
+    SystemStatistic::SoftirqSched,
+    SystemStatistic::SoftirqHrtimer,
+    SystemStatistic::SoftirqRcu,
+];
+
+impl std::fmt::Display for SystemStatistic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SystemStatistic::ContextSwitches => write!(f, "system/context_switches"),
+            SystemStatistic::Interrupts => write!(f, "system/interrupts"),
+            SystemStatistic::Softirqs => write!(f, "system/softirq/total"),
+            SystemStatistic::SoftirqHi => write!(f, "system/softirq/hi"),
+            SystemStatistic::SoftirqTimer => write!(f, "system/softirq/timer"),
+            SystemStatistic::SoftirqNetTx => write!(f, "system/softirq/net_tx"),
+            SystemStatistic::SoftirqNetRx => write!(f, "system/softirq/net_rx"),
+            SystemStatistic::SoftirqBlock => write!(f, "system/softirq/block"),
+            SystemStatistic::SoftirqIrqPoll => write!(f, "system/softirq/irq_poll"),
+            SystemStatistic::SoftirqTasklet => write!(f, "system/softirq/tasklet"),
+            SystemStatistic::SoftirqSched => write!(f, "system/softirq/sched"),
+            SystemStatistic::SoftirqHrtimer => write!(f, "system/softirq/hrtimer"),
+            SystemStatistic::SoftirqRcu => write!(f, "system/softirq/rcu"),
+            SystemStatistic::Forks => write!(f, "system/forks"),
+            SystemStatistic::ProcessesRunning => write!(f, "system/processes/running"),
+            SystemStatistic::ProcessesBlocked => write!(f, "system/processes/blocked"),
+            SystemStatistic::BootTime => write!(f, "system/boot_time"),
+        }
+    }
+}
+
+impl Statistic for SystemStatistic {
+    fn description(&self) -> &str {
+        match self {
+            SystemStatistic::ContextSwitches => "number of context switches",
+            SystemStatistic::Interrupts => "number of interrupts serviced",
+            SystemStatistic::Softirqs => "number of softirqs serviced",
+            SystemStatistic::SoftirqHi => "number of high-priority tasklet softirqs serviced",
+            SystemStatistic::SoftirqTimer => "number of timer softirqs serviced",
+            SystemStatistic::SoftirqNetTx => "number of network transmit softirqs serviced",
+            SystemStatistic::SoftirqNetRx => "number of network receive softirqs serviced",
+            SystemStatistic::SoftirqBlock => "number of block device softirqs serviced",
+            SystemStatistic::SoftirqIrqPoll => "number of IO polling softirqs serviced",
+            SystemStatistic::SoftirqTasklet => "number of tasklet softirqs serviced",
+            SystemStatistic::SoftirqSched => "number of scheduler softirqs serviced",
+            SystemStatistic::SoftirqHrtimer => "number of high-resolution timer softirqs serviced",
+            SystemStatistic::SoftirqRcu => "number of RCU softirqs serviced",
+            SystemStatistic::Forks => "number of processes and threads created",
+            SystemStatistic::ProcessesRunning => "number of processes which are runnable",
+            SystemStatistic::ProcessesBlocked => "number of processes blocked waiting on IO",
+            SystemStatistic::BootTime => "time, in seconds since the epoch, when the system booted",
+        }
+    }
+
+    fn unit(&self) -> Option<Unit> {
+        match self {
+            SystemStatistic::BootTime => Some(Unit::Seconds),
+            _ => None,
+        }
+    }
+}
+
+impl SystemStatistic {
+    /// The maximum secondly rate, or value for gauges, which is tracked
+    pub fn max(&self) -> u64 {
+        match self {
+            SystemStatistic::Forks
+            | SystemStatistic::ProcessesRunning
+            | SystemStatistic::ProcessesBlocked => MILLION,
+            SystemStatistic::BootTime => TRILLION,
+            _ => BILLION,
+        }
+    }
+
+    pub fn is_gauge(&self) -> bool {
+        match self {
+            SystemStatistic::ProcessesRunning
+            | SystemStatistic::ProcessesBlocked
+            | SystemStatistic::BootTime => true,
+            _ => false,
+        }
+    }
+}
+
+/// Parses a line of `/proc/stat` other than the `cpu` lines into the
+/// statistics it reports. The counts for each IRQ on the `intr` line are
+/// added to `irqs`
+pub(super) fn parse_system_line(
+    parts: &[&str],
+    statistics: &mut HashMap<SystemStatistic, u64>,
+    irqs: &mut HashMap<usize, u64>,
+) {
+    let value = |index: usize| parts.get(index).and_then(|v| v.parse().ok());
+    let statistic = match parts.first() {
+        Some(&"ctxt") => SystemStatistic::ContextSwitches,
+        Some(&"processes") => SystemStatistic::Forks,
+        Some(&"procs_running") => SystemStatistic::ProcessesRunning,
+        Some(&"procs_blocked") => SystemStatistic::ProcessesBlocked,
+        Some(&"btime") => SystemStatistic::BootTime,
+        Some(&"intr") => {
+            for (irq, count) in parts.iter().skip(2).enumerate() {
+                if let Ok(count) = count.parse() {
+                    irqs.insert(irq, count);
+                }
+            }
+            SystemStatistic::Interrupts
+        }
+        Some(&"softirq") => {
+            // older kernels report fewer types of softirq
+            for (index, softirq) in SOFTIRQS.iter().enumerate() {
+                if let Some(count) = value(index + 2) {
+                    statistics.insert(softirq.clone(), count);
+                }
+            }
+            SystemStatistic::Softirqs
+        }
+        _ => return,
+    };
+    if let Some(value) = value(1) {
+        statistics.insert(statistic, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_system_lines() {
+        let mut statistics = HashMap::new();
+        let mut irqs = HashMap::new();
+        for line in &[
+            "intr 4268467 12 0 3",
+            "ctxt 4744363",
+            "softirq 6691885 168674 1769433 4256",
+            "procs_blocked 0",
+            "page 5741 1808",
+        ] {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            parse_system_line(&parts, &mut statistics, &mut irqs);
+        }
+        assert_eq!(statistics[&SystemStatistic::Interrupts], 4268467);
+        assert_eq!(statistics[&SystemStatistic::ContextSwitches], 4744363);
+        assert_eq!(statistics[&SystemStatistic::Softirqs], 6691885);
+        assert_eq!(statistics[&SystemStatistic::SoftirqTimer], 1769433);
+        assert_eq!(statistics[&SystemStatistic::SoftirqNetTx], 4256);
+        assert_eq!(statistics.get(&SystemStatistic::SoftirqNetRx), None);
+        assert_eq!(statistics[&SystemStatistic::ProcessesBlocked], 0);
+        assert_eq!(statistics.len(), 7);
+        assert_eq!(irqs[&0], 12);
+        assert_eq!(irqs[&2], 3);
+    }
+}
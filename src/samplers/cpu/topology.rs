@@ -0,0 +1,77 @@
+// Copyright 2019 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use crate::common::file;
+
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The socket and NUMA node of each CPU, as found in
+/// `/sys/devices/system/cpu/cpu*`
+#[derive(Clone, Debug, Default)]
+pub struct Topology {
+    sockets: HashMap<usize, u64>,
+    nodes: HashMap<usize, u64>,
+}
+
+impl Topology {
+    pub fn new(sys_root: &Path) -> Self {
+        let mut topology = Topology::default();
+        let entries = match std::fs::read_dir(sys_root.join("devices/system/cpu")) {
+            Ok(entries) => entries,
+            Err(_) => return topology,
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let cpu: usize = match id(&name, "cpu") {
+                Some(cpu) => cpu,
+                None => continue,
+            };
+            if let Ok(socket) = file::file_as_u64(entry.path().join("topology/physical_package_id"))
+            {
+                topology.sockets.insert(cpu, socket);
+            }
+            // the node is a link named for it within the directory of the cpu
+            if let Ok(links) = std::fs::read_dir(entry.path()) {
+                for link in links.filter_map(Result::ok) {
+                    if let Some(node) = id(&link.file_name().to_string_lossy(), "node") {
+                        topology.nodes.insert(cpu, node as u64);
+                    }
+                }
+            }
+        }
+        topology
+    }
+
+    /// The physical package which contains the CPU
+    pub fn socket(&self, cpu: usize) -> Option<u64> {
+        self.sockets.get(&cpu).cloned()
+    }
+
+    /// The NUMA node which contains the CPU
+    pub fn node(&self, cpu: usize) -> Option<u64> {
+        self.nodes.get(&cpu).cloned()
+    }
+}
+
+/// Parses the id from a name such as `cpu3` or `node0`
+fn id(name: &str, prefix: &str) -> Option<usize> {
+    name.strip_prefix(prefix)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_topology() {
+        let topology = Topology::new(Path::new("tests/data/sys"));
+        assert_eq!(topology.socket(0), Some(0));
+        assert_eq!(topology.socket(3), Some(1));
+        assert_eq!(topology.node(1), Some(0));
+        assert_eq!(topology.node(2), Some(1));
+        assert_eq!(topology.socket(4), None);
+        assert_eq!(id("cpufreq", "cpu"), None);
+    }
+}
@@ -0,0 +1,282 @@
+// Copyright 2019 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use crate::common::file::{file_as_u64, string_from_file};
+use crate::common::*;
+use crate::config::Config;
+use crate::samplers::Statistic as _;
+use crate::samplers::{construct, Registration, Sampler, SamplerError};
+use crate::stats::{
+    delete_channel, delete_distribution, record_counter, record_distribution, register_counter,
+    register_distribution, Unit,
+};
+use failure::Error;
+
+use logger::*;
+use metrics::*;
+use serde_derive::*;
+use time;
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+
+const CPU: &str = "devices/system/cpu";
+
+pub const REGISTRATION: Registration = Registration {
+    name: "cpupower",
+    section: "cpupower",
+    config: |config| config.cpupower(),
+    changed: |old, new| old.cpupower() != new.cpupower(),
+    enabled: |config| config.cpupower().enabled(),
+    new: construct::<Cpupower>,
+};
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Hash)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub enum Statistic {
+    Frequency,
+    CstateTime,
+    CstateUsage,
+}
+
+impl std::fmt::Display for Statistic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Statistic::Frequency => write!(f, "cpu/frequency"),
+            Statistic::CstateTime => write!(f, "cpu/cstate/time"),
+            Statistic::CstateUsage => write!(f, "cpu/cstate/usage"),
+        }
+    }
+}
+
+impl crate::samplers::Statistic for Statistic {
+    fn description(&self) -> &str {
+        match self {
+            Statistic::Frequency => "current frequency, in hertz, of each CPU",
+            Statistic::CstateTime => "time, in nanoseconds, spent by the CPUs in the idle state",
+            Statistic::CstateUsage => "number of times the CPUs entered the idle state",
+        }
+    }
+
+    fn unit(&self) -> Option<Unit> {
+        match self {
+            Statistic::CstateTime => Some(Unit::Nanoseconds),
+            _ => None,
+        }
+    }
+}
+
+/// The time spent in, and number of entries into, an idle state summed
+/// across CPUs
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Cstate {
+    time: u64,
+    usage: u64,
+}
+
+/// A reading of the frequency and idle states of the CPUs
+#[derive(Debug, Default)]
+pub struct Reading {
+    /// the current frequency, in hertz, of each CPU which reports it
+    frequencies: Vec<u64>,
+    /// the idle states, by name, such as `C1` or `C6`
+    cstates: HashMap<String, Cstate>,
+}
+
+/// Reads `cpufreq` and `cpuidle` for each CPU in `/sys/devices/system/cpu`.
+/// CPUs without frequency scaling or an idle driver are skipped
+pub fn read_cpupower(sys_root: &Path) -> Result<Reading, SamplerError> {
+    let path = sys_root.join(CPU);
+    let entries = std::fs::read_dir(&path).map_err(|e| {
+        SamplerError::Transient(format!("could not read {}: {}", path.display(), e))
+    })?;
+    let mut reading = Reading::default();
+    for entry in entries.filter_map(Result::ok) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        match name.strip_prefix("cpu").map(str::parse::<usize>) {
+            Some(Ok(_)) => {}
+            _ => continue,
+        }
+        let cpu = entry.path();
+        // scaling_cur_freq is in kilohertz
+        if let Ok(frequency) = file_as_u64(cpu.join("cpufreq/scaling_cur_freq")) {
+            reading.frequencies.push(frequency * THOUSAND);
+        }
+        let states = match std::fs::read_dir(cpu.join("cpuidle")) {
+            Ok(states) => states,
+            Err(_) => continue,
+        };
+        for state in states.filter_map(Result::ok) {
+            let state = state.path();
+            let name = match string_from_file(state.join("name")) {
+                Ok(name) => name,
+                Err(_) => continue,
+            };
+            let cstate = reading.cstates.entry(name).or_default();
+            // time is in microseconds
+            cstate.time += file_as_u64(state.join("time")).unwrap_or(0) * MICROSECOND;
+            cstate.usage += file_as_u64(state.join("usage")).unwrap_or(0);
+        }
+    }
+    Ok(reading)
+}
+
+pub struct Cpupower {
+    config: Arc<Config>,
+    initialized: bool,
+    recorder: Recorder<AtomicU32>,
+    /// the idle states for which channels are registered
+    registered: HashSet<String>,
+    /// the statistics which were registered, as the config may be reloaded
+    statistics: Vec<Statistic>,
+    /// the number of hardware threads, which bounds the idle state counters
+    cores: u64,
+}
+
+impl Cpupower {
+    /// register the channels of the statistics of an idle state
+    fn register_cstate(&self, cstate: &str) {
+        for statistic in &self.statistics {
+            let max = match statistic {
+                Statistic::CstateTime => 2 * self.cores * SECOND,
+                Statistic::CstateUsage => self.cores * MILLION,
+                Statistic::Frequency => continue,
+            };
+            register_counter(
+                &self.recorder,
+                statistic.with_label("state", cstate),
+                max,
+                3,
+                self.config.general().window(),
+                PERCENTILES,
+            );
+        }
+    }
+
+    fn deregister_cstate(&self, cstate: &str) {
+        for statistic in &self.statistics {
+            if *statistic != Statistic::Frequency {
+                delete_channel(
+                    &self.recorder,
+                    statistic.with_label("state", cstate).to_string(),
+                );
+            }
+        }
+    }
+}
+
+impl Sampler for Cpupower {
+    fn new(config: Arc<Config>, recorder: Recorder<AtomicU32>) -> Result<Option<Box<Self>>, Error> {
+        if config.cpupower().enabled() {
+            Ok(Some(Box::new(Self {
+                config,
+                initialized: false,
+                recorder,
+                registered: HashSet::new(),
+                statistics: Vec::new(),
+                cores: 1,
+            })))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn name(&self) -> String {
+        "cpupower".to_string()
+    }
+
+    fn sample(&mut self) -> Result<(), SamplerError> {
+        trace!("sample {}", self.name());
+        let reading = read_cpupower(self.config.general().sys_root())?;
+        let time = time::precise_time_ns();
+        if !self.initialized {
+            self.register();
+        }
+        if self.statistics.contains(&Statistic::Frequency) {
+            for frequency in &reading.frequencies {
+                record_distribution(&self.recorder, Statistic::Frequency, time, *frequency, 1);
+            }
+        }
+        for (name, cstate) in &reading.cstates {
+            // idle states are registered on sight, as they depend on the
+            // idle driver of the host
+            if !self.registered.contains(name) {
+                self.register_cstate(name);
+                self.registered.insert(name.clone());
+            }
+            for statistic in &self.statistics {
+                let value = match statistic {
+                    Statistic::CstateTime => cstate.time,
+                    Statistic::CstateUsage => cstate.usage,
+                    Statistic::Frequency => continue,
+                };
+                record_counter(
+                    &self.recorder,
+                    statistic.with_label("state", name),
+                    time,
+                    value,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn register(&mut self) {
+        trace!("register {}", self.name());
+        if !self.initialized {
+            self.statistics = self.config.cpupower().statistics();
+            self.cores = hardware_threads(self.config.general().sys_root()).unwrap_or(1);
+            if self.statistics.contains(&Statistic::Frequency) {
+                register_distribution(
+                    &self.recorder,
+                    Statistic::Frequency,
+                    10 * BILLION,
+                    2,
+                    self.config.general().window(),
+                    PERCENTILES,
+                );
+            }
+            self.initialized = true;
+        }
+    }
+
+    fn deregister(&mut self) {
+        trace!("deregister {}", self.name());
+        if self.initialized {
+            if self.statistics.contains(&Statistic::Frequency) {
+                delete_distribution(&self.recorder, Statistic::Frequency);
+            }
+            for cstate in &self.registered {
+                self.deregister_cstate(cstate);
+            }
+            self.registered.clear();
+            self.initialized = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_cpupower_from_root() {
+        let mut reading = read_cpupower(Path::new("tests/data/sys")).unwrap();
+        reading.frequencies.sort();
+        assert_eq!(
+            reading.frequencies,
+            vec![1_200_000_000, 2_400_000_000, 3_100_000_000]
+        );
+        assert_eq!(reading.cstates.len(), 3);
+        assert_eq!(
+            reading.cstates["C6"],
+            Cstate {
+                time: 16_000_000 * MICROSECOND,
+                usage: 3000,
+            }
+        );
+        assert_eq!(reading.cstates["POLL"].usage, 30);
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright 2019 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use super::{Entry, Statistic};
+use crate::common::{BILLION, MILLION, MINUTE, SECOND};
+use crate::stats::Unit;
+
+use serde_derive::*;
+
+/// Statistics, as reported by `iostat -x`, which are derived from the change
+/// in an `Entry` between consecutive samples
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Hash)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub enum DerivedStatistic {
+    Utilization,
+    AwaitRead,
+    AwaitWrite,
+    RequestSize,
+    QueueSize,
+}
+
+impl std::fmt::Display for DerivedStatistic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DerivedStatistic::Utilization => write!(f, "disk/utilization"),
+            DerivedStatistic::AwaitRead => write!(f, "disk/await/read"),
+            DerivedStatistic::AwaitWrite => write!(f, "disk/await/write"),
+            DerivedStatistic::RequestSize => write!(f, "disk/request/size"),
+            DerivedStatistic::QueueSize => write!(f, "disk/queue/size"),
+        }
+    }
+}
+
+impl crate::samplers::Statistic for DerivedStatistic {
+    fn description(&self) -> &str {
+        match self {
+            DerivedStatistic::Utilization => {
+                "percentage of time where IOs were in flight, averaged across devices"
+            }
+            DerivedStatistic::AwaitRead => "average time, in nanoseconds, to complete a read",
+            DerivedStatistic::AwaitWrite => "average time, in nanoseconds, to complete a write",
+            DerivedStatistic::RequestSize => "average size, in bytes, of the IOs completed",
+            DerivedStatistic::QueueSize => "average number of IOs in flight, in hundredths",
+        }
+    }
+
+    fn unit(&self) -> Option<Unit> {
+        match self {
+            DerivedStatistic::AwaitRead | DerivedStatistic::AwaitWrite => Some(Unit::Nanoseconds),
+            DerivedStatistic::RequestSize => Some(Unit::Bytes),
+            _ => None,
+        }
+    }
+}
+
+impl DerivedStatistic {
+    /// The maximum value which is tracked
+    pub fn max(&self) -> u64 {
+        match self {
+            DerivedStatistic::Utilization => 100,
+            DerivedStatistic::AwaitRead | DerivedStatistic::AwaitWrite => MINUTE,
+            DerivedStatistic::RequestSize => BILLION,
+            DerivedStatistic::QueueSize => MILLION,
+        }
+    }
+
+    /// Computes the statistic from the `delta` between two samples taken
+    /// `elapsed` nanoseconds apart, summed across the given number of
+    /// `devices`. Ratios without any IOs are zero
+    pub fn compute(&self, delta: &Entry, elapsed: u64, devices: u64) -> u64 {
+        let ratio = |numerator: u64, denominator: u64| {
+            if denominator == 0 {
+                0
+            } else {
+                numerator / denominator
+            }
+        };
+        match self {
+            DerivedStatistic::Utilization => ratio(
+                100 * delta.get(&Statistic::IoTicks),
+                elapsed.saturating_mul(devices),
+            )
+            .min(100),
+            DerivedStatistic::AwaitRead => ratio(
+                delta.get(&Statistic::TimeRead),
+                delta.get(&Statistic::OperationsRead),
+            ),
+            DerivedStatistic::AwaitWrite => ratio(
+                delta.get(&Statistic::TimeWrite),
+                delta.get(&Statistic::OperationsWrite),
+            ),
+            DerivedStatistic::RequestSize => ratio(
+                delta.get(&Statistic::BandwidthRead) + delta.get(&Statistic::BandwidthWrite),
+                delta.get(&Statistic::OperationsRead) + delta.get(&Statistic::OperationsWrite),
+            ),
+            DerivedStatistic::QueueSize => ratio(100 * delta.get(&Statistic::TimeInQueue), elapsed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::MILLISECOND;
+
+    #[test]
+    fn compute() {
+        // one second in which 100 reads of 4KiB each took 2ms, 50 writes of
+        // 8KiB each took 10ms, and the device was busy for half the time
+        let previous = Entry::parse("0 0 0 0 0 0 0 0 0 0 0").unwrap();
+        let current = Entry::parse("100 0 800 200 50 0 800 500 0 500 1500").unwrap();
+        let delta = current.delta(&previous).unwrap();
+        let compute = |statistic: DerivedStatistic| statistic.compute(&delta, SECOND, 1);
+        assert_eq!(compute(DerivedStatistic::Utilization), 50);
+        assert_eq!(compute(DerivedStatistic::AwaitRead), 2 * MILLISECOND);
+        assert_eq!(compute(DerivedStatistic::AwaitWrite), 10 * MILLISECOND);
+        assert_eq!(compute(DerivedStatistic::RequestSize), 1600 * 512 / 150);
+        assert_eq!(compute(DerivedStatistic::QueueSize), 150);
+        // utilization is averaged across devices
+        assert_eq!(DerivedStatistic::Utilization.compute(&delta, SECOND, 2), 25);
+    }
+
+    #[test]
+    fn compute_idle() {
+        let delta = Entry::default();
+        assert_eq!(DerivedStatistic::AwaitRead.compute(&delta, SECOND, 1), 0);
+        assert_eq!(DerivedStatistic::RequestSize.compute(&delta, SECOND, 1), 0);
+        assert_eq!(DerivedStatistic::Utilization.compute(&delta, 0, 1), 0);
+    }
+}
@@ -2,7 +2,10 @@
 // Licensed under the Apache License, Version 2.0
 // http://www.apache.org/licenses/LICENSE-2.0
 
+use serde_derive::*;
+
 use std::fmt;
+use std::path::Path;
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct Device {
@@ -27,3 +30,50 @@ impl Device {
         self.name.clone()
     }
 }
+
+/// The kinds of block device which the disk sampler may monitor
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Hash)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub enum DeviceType {
+    /// a whole physical disk, such as `sda` or `nvme0n1`
+    Disk,
+    /// a partition of a disk, such as `sda1` or `nvme0n1p1`
+    Partition,
+    /// a device without backing hardware, such as `loop0`, `dm-0` or `md0`
+    Virtual,
+}
+
+impl DeviceType {
+    /// Classifies the named block device by its entries in sysfs. Returns
+    /// `None` if it is not a block device
+    pub fn of(sys_root: &Path, name: &str) -> Option<DeviceType> {
+        let device = sys_root.join("class/block").join(name);
+        if device.join("partition").exists() {
+            Some(DeviceType::Partition)
+        } else if sys_root.join("devices/virtual/block").join(name).exists() {
+            Some(DeviceType::Virtual)
+        } else if sys_root.join("block").join(name).exists() {
+            Some(DeviceType::Disk)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify() {
+        let sys_root = Path::new("tests/data/sys");
+        let of = |name| DeviceType::of(sys_root, name);
+        assert_eq!(of("sda"), Some(DeviceType::Disk));
+        assert_eq!(of("nvme0n1"), Some(DeviceType::Disk));
+        assert_eq!(of("sda1"), Some(DeviceType::Partition));
+        assert_eq!(of("nvme0n1p1"), Some(DeviceType::Partition));
+        assert_eq!(of("dm-0"), Some(DeviceType::Virtual));
+        assert_eq!(of("loop0"), Some(DeviceType::Virtual));
+        assert_eq!(of("missing"), None);
+    }
+}
@@ -3,66 +3,112 @@
 // http://www.apache.org/licenses/LICENSE-2.0
 
 use super::*;
-use crate::common::{file, SECTOR_SIZE};
+use crate::common::{file, MILLISECOND, SECTOR_SIZE};
 use std::iter::Sum;
-use std::ops::{Add, Sub};
+use std::ops::Add;
+use std::path::Path;
 
-#[derive(Clone, Copy, Debug)]
+/// The fields of `/sys/class/block/(device)/stat`, with sectors converted to
+/// bytes and milliseconds converted to nanoseconds
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Entry {
     read_bytes: u64,
     read_ops: u64,
+    read_merges: u64,
+    read_time: u64,
     write_bytes: u64,
     write_ops: u64,
+    write_merges: u64,
+    write_time: u64,
+    in_flight: u64,
+    io_ticks: u64,
+    time_in_queue: u64,
 }
 
 impl Entry {
-    pub fn read_bytes(&self) -> u64 {
-        self.read_bytes
-    }
-
-    pub fn write_bytes(&self) -> u64 {
-        self.write_bytes
-    }
-
-    pub fn read_ops(&self) -> u64 {
-        self.read_ops
-    }
-
-    pub fn write_ops(&self) -> u64 {
-        self.write_ops
+    /// Returns the value of the field which backs the `Statistic`
+    pub fn get(&self, statistic: &Statistic) -> u64 {
+        match statistic {
+            Statistic::BandwidthRead => self.read_bytes,
+            Statistic::BandwidthWrite => self.write_bytes,
+            Statistic::OperationsRead => self.read_ops,
+            Statistic::OperationsWrite => self.write_ops,
+            Statistic::MergesRead => self.read_merges,
+            Statistic::MergesWrite => self.write_merges,
+            Statistic::TimeRead => self.read_time,
+            Statistic::TimeWrite => self.write_time,
+            Statistic::InFlight => self.in_flight,
+            Statistic::IoTicks => self.io_ticks,
+            Statistic::TimeInQueue => self.time_in_queue,
+        }
     }
 
-    pub fn for_device(device: &Device) -> Self {
+    pub fn for_device(sys_root: &Path, device: &Device) -> Self {
+        let name = match device.name() {
+            Some(name) => name,
+            None => return Entry::default(),
+        };
         if let Ok(content) =
-            file::string_from_file(format!("/sys/class/block/{}/stat", device.name().unwrap()))
+            file::string_from_file(sys_root.join(format!("class/block/{}/stat", name)))
         {
-            let parts: Vec<&str> = content.split_whitespace().collect();
-            if parts.len() != 11 {
-                debug!(
-                    "Unable to parse stats for block device: {}",
-                    device.name().unwrap_or_else(|| "Total".to_owned())
-                );
-            }
-            Entry {
-                read_ops: parts[0].parse().unwrap_or(0),
-                read_bytes: parts[2].parse().unwrap_or(0) * SECTOR_SIZE,
-                write_ops: parts[4].parse().unwrap_or(0),
-                write_bytes: parts[6].parse().unwrap_or(0) * SECTOR_SIZE,
+            match Entry::parse(&content) {
+                Some(entry) => entry,
+                None => {
+                    debug!("Unable to parse stats for block device: {}", name);
+                    Entry::default()
+                }
             }
         } else {
             Entry::default()
         }
     }
-}
 
-impl Default for Entry {
-    fn default() -> Self {
-        Self {
-            read_bytes: 0,
-            read_ops: 0,
-            write_bytes: 0,
-            write_ops: 0,
+    /// Parses the content of a `stat` file. Older kernels only provide the
+    /// first seven fields, in which case the rest are zero
+    pub(super) fn parse(content: &str) -> Option<Self> {
+        let parts: Vec<u64> = content
+            .split_whitespace()
+            .map(|part| part.parse().unwrap_or(0))
+            .collect();
+        if parts.len() < 7 {
+            return None;
         }
+        let field = |index: usize| parts.get(index).cloned().unwrap_or(0);
+        Some(Entry {
+            read_ops: field(0),
+            read_merges: field(1),
+            read_bytes: field(2) * SECTOR_SIZE,
+            read_time: field(3) * MILLISECOND,
+            write_ops: field(4),
+            write_merges: field(5),
+            write_bytes: field(6) * SECTOR_SIZE,
+            write_time: field(7) * MILLISECOND,
+            in_flight: field(8),
+            io_ticks: field(9) * MILLISECOND,
+            time_in_queue: field(10) * MILLISECOND,
+        })
+    }
+}
+
+impl Entry {
+    /// Returns the change in each counter since the `previous` reading, along
+    /// with the current number of IOs in flight. Returns `None` if any
+    /// counter went backwards, as when a device is removed and added again or
+    /// its counters are reset, as the change over the interval is unknown
+    pub fn delta(&self, previous: &Entry) -> Option<Entry> {
+        Some(Entry {
+            read_bytes: self.read_bytes.checked_sub(previous.read_bytes)?,
+            read_ops: self.read_ops.checked_sub(previous.read_ops)?,
+            read_merges: self.read_merges.checked_sub(previous.read_merges)?,
+            read_time: self.read_time.checked_sub(previous.read_time)?,
+            write_bytes: self.write_bytes.checked_sub(previous.write_bytes)?,
+            write_ops: self.write_ops.checked_sub(previous.write_ops)?,
+            write_merges: self.write_merges.checked_sub(previous.write_merges)?,
+            write_time: self.write_time.checked_sub(previous.write_time)?,
+            in_flight: self.in_flight,
+            io_ticks: self.io_ticks.checked_sub(previous.io_ticks)?,
+            time_in_queue: self.time_in_queue.checked_sub(previous.time_in_queue)?,
+        })
     }
 }
 
@@ -73,8 +119,15 @@ impl Add for Entry {
         Entry {
             read_bytes: self.read_bytes.wrapping_add(rhs.read_bytes),
             read_ops: self.read_ops.wrapping_add(rhs.read_ops),
+            read_merges: self.read_merges.wrapping_add(rhs.read_merges),
+            read_time: self.read_time.wrapping_add(rhs.read_time),
             write_bytes: self.write_bytes.wrapping_add(rhs.write_bytes),
             write_ops: self.write_ops.wrapping_add(rhs.write_ops),
+            write_merges: self.write_merges.wrapping_add(rhs.write_merges),
+            write_time: self.write_time.wrapping_add(rhs.write_time),
+            in_flight: self.in_flight.wrapping_add(rhs.in_flight),
+            io_ticks: self.io_ticks.wrapping_add(rhs.io_ticks),
+            time_in_queue: self.time_in_queue.wrapping_add(rhs.time_in_queue),
         }
     }
 }
@@ -89,12 +142,7 @@ impl<'a> Add<&'a Entry> for Entry {
     type Output = Entry;
 
     fn add(self, rhs: &'a Entry) -> Entry {
-        Entry {
-            read_bytes: self.read_bytes.wrapping_add(rhs.read_bytes),
-            read_ops: self.read_ops.wrapping_add(rhs.read_ops),
-            write_bytes: self.write_bytes.wrapping_add(rhs.write_bytes),
-            write_ops: self.write_ops.wrapping_add(rhs.write_ops),
-        }
+        self + *rhs
     }
 }
 
@@ -102,24 +150,52 @@ impl<'a, 'b> Add<&'b Entry> for &'a Entry {
     type Output = Entry;
 
     fn add(self, rhs: &'b Entry) -> Entry {
-        Entry {
-            read_bytes: self.read_bytes.wrapping_add(rhs.read_bytes),
-            read_ops: self.read_ops.wrapping_add(rhs.read_ops),
-            write_bytes: self.write_bytes.wrapping_add(rhs.write_bytes),
-            write_ops: self.write_ops.wrapping_add(rhs.write_ops),
-        }
+        *self + *rhs
     }
 }
 
-impl Sub for Entry {
-    type Output = Entry;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_stat() {
+        let entry = Entry::parse(
+            "    4305      123   312930     2017     1057       88    43520     5324        2     3052     7341\n",
+        )
+        .unwrap();
+        assert_eq!(entry.get(&Statistic::OperationsRead), 4305);
+        assert_eq!(entry.get(&Statistic::MergesRead), 123);
+        assert_eq!(entry.get(&Statistic::BandwidthRead), 312930 * SECTOR_SIZE);
+        assert_eq!(entry.get(&Statistic::TimeRead), 2017 * MILLISECOND);
+        assert_eq!(entry.get(&Statistic::OperationsWrite), 1057);
+        assert_eq!(entry.get(&Statistic::MergesWrite), 88);
+        assert_eq!(entry.get(&Statistic::BandwidthWrite), 43520 * SECTOR_SIZE);
+        assert_eq!(entry.get(&Statistic::TimeWrite), 5324 * MILLISECOND);
+        assert_eq!(entry.get(&Statistic::InFlight), 2);
+        assert_eq!(entry.get(&Statistic::IoTicks), 3052 * MILLISECOND);
+        assert_eq!(entry.get(&Statistic::TimeInQueue), 7341 * MILLISECOND);
+    }
 
-    fn sub(self, rhs: Entry) -> Entry {
-        Entry {
-            read_bytes: self.read_bytes.wrapping_sub(rhs.read_bytes),
-            read_ops: self.read_ops.wrapping_sub(rhs.read_ops),
-            write_bytes: self.write_bytes.wrapping_sub(rhs.write_bytes),
-            write_ops: self.write_ops.wrapping_sub(rhs.write_ops),
-        }
+    #[test]
+    fn parse_short_stat() {
+        let entry = Entry::parse("1 0 8 0 2 0 16").unwrap();
+        assert_eq!(entry.get(&Statistic::BandwidthRead), 8 * SECTOR_SIZE);
+        assert_eq!(entry.get(&Statistic::BandwidthWrite), 16 * SECTOR_SIZE);
+        assert_eq!(entry.get(&Statistic::InFlight), 0);
+        assert!(Entry::parse("1 0 8").is_none());
+    }
+
+    #[test]
+    fn delta() {
+        let previous = Entry::parse("10 0 80 5 20 0 160 8 3 40 50").unwrap();
+        let current = Entry::parse("15 0 120 7 20 0 160 8 1 45 60").unwrap();
+        let delta = current.delta(&previous).unwrap();
+        assert_eq!(delta.get(&Statistic::OperationsRead), 5);
+        assert_eq!(delta.get(&Statistic::BandwidthRead), 40 * SECTOR_SIZE);
+        assert_eq!(delta.get(&Statistic::OperationsWrite), 0);
+        assert_eq!(delta.get(&Statistic::InFlight), 1);
+        // the counters of a device which was added again start from zero
+        assert_eq!(previous.delta(&current), None);
     }
 }
@@ -2,45 +2,84 @@
 // Licensed under the Apache License, Version 2.0
 // http://www.apache.org/licenses/LICENSE-2.0
 
+mod derived;
 mod device;
 mod entry;
 
-pub use self::device::Device;
+pub use self::derived::DerivedStatistic;
+pub use self::device::{Device, DeviceType};
 pub use self::entry::Entry;
-use crate::stats::record_counter;
-use crate::stats::register_counter;
+use crate::samplers::Statistic as _;
+use crate::stats::{
+    delete_channel, record_counter, record_gauge, register_counter, register_gauge, Labeled, Unit,
+};
 use failure::Error;
 
 use crate::common::*;
 use crate::config::Config;
-use crate::samplers::Sampler;
+use crate::samplers::{construct, Registration, Sampler, SamplerError};
 
 use logger::*;
 use metrics::*;
-use regex::Regex;
 use serde_derive::*;
 use time;
 use walkdir::WalkDir;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 const REFRESH: u64 = 60_000_000_000;
 
-pub struct Disk<'a> {
-    config: &'a Config,
-    devices: Vec<Device>,
+pub const REGISTRATION: Registration = Registration {
+    name: "disk",
+    section: "disk",
+    config: |config| config.disk(),
+    changed: |old, new| old.disk() != new.disk(),
+    enabled: |config| config.disk().enabled(),
+    new: construct::<Disk>,
+};
+
+pub struct Disk {
+    config: Arc<Config>,
+    /// the monitored devices and their types
+    devices: Vec<(Device, DeviceType)>,
+    filter: Filter,
     initialized: bool,
     last_refreshed: u64,
-    recorder: &'a Recorder<AtomicU32>,
+    recorder: Recorder<AtomicU32>,
+    /// the devices for which per-device channels are registered
+    registered: HashSet<Device>,
+    /// the statistics which were registered, as the config may be reloaded
+    statistics: Vec<Statistic>,
+    derived_statistics: Vec<DerivedStatistic>,
+    /// the reading and time of the last sample of each device, and of the
+    /// total across devices
+    previous: HashMap<Device, (u64, Entry)>,
+}
+
+/// Labels the statistic with the name of the `Device`, unless it is the total
+/// across devices
+fn labeled<T: crate::samplers::Statistic>(statistic: T, device: &Device) -> Labeled<T> {
+    match device.name() {
+        Some(name) => statistic.per("device", name),
+        None => Labeled::new(statistic),
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Hash)]
-#[serde(deny_unknown_fields, rename_all = "lowercase")]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
 pub enum Statistic {
     BandwidthRead,
     BandwidthWrite,
     OperationsRead,
     OperationsWrite,
+    MergesRead,
+    MergesWrite,
+    TimeRead,
+    TimeWrite,
+    InFlight,
+    IoTicks,
+    TimeInQueue,
 }
 
 impl std::fmt::Display for Statistic {
@@ -50,71 +89,217 @@ impl std::fmt::Display for Statistic {
             Statistic::BandwidthWrite => write!(f, "disk/bandwidth/write"),
             Statistic::OperationsRead => write!(f, "disk/operations/read"),
             Statistic::OperationsWrite => write!(f, "disk/operations/write"),
+            Statistic::MergesRead => write!(f, "disk/merges/read"),
+            Statistic::MergesWrite => write!(f, "disk/merges/write"),
+            Statistic::TimeRead => write!(f, "disk/time/read"),
+            Statistic::TimeWrite => write!(f, "disk/time/write"),
+            Statistic::InFlight => write!(f, "disk/in_flight"),
+            Statistic::IoTicks => write!(f, "disk/time/io"),
+            Statistic::TimeInQueue => write!(f, "disk/time/queue"),
         }
     }
 }
 
-impl<'a> Disk<'a> {
-    /// send deltas to the stats library
-    fn record(&self, time: u64, recorder: &Recorder<AtomicU32>, reading: Entry) {
-        record_counter(
-            recorder,
-            Statistic::BandwidthRead,
-            time,
-            reading.read_bytes(),
-        );
-        record_counter(
-            recorder,
-            Statistic::BandwidthWrite,
-            time,
-            reading.write_bytes(),
-        );
-        record_counter(
-            recorder,
-            Statistic::OperationsRead,
-            time,
-            reading.read_ops(),
-        );
-        record_counter(
-            recorder,
-            Statistic::OperationsWrite,
-            time,
-            reading.write_ops(),
-        );
+impl crate::samplers::Statistic for Statistic {
+    fn description(&self) -> &str {
+        match self {
+            Statistic::BandwidthRead => "number of bytes read from disk",
+            Statistic::BandwidthWrite => "number of bytes written to disk",
+            Statistic::OperationsRead => "number of IOs servicing reads",
+            Statistic::OperationsWrite => "number of IOs servicing writes",
+            Statistic::MergesRead => "number of reads merged with an adjacent read",
+            Statistic::MergesWrite => "number of writes merged with an adjacent write",
+            Statistic::TimeRead => "time, in nanoseconds, spent waiting on reads",
+            Statistic::TimeWrite => "time, in nanoseconds, spent waiting on writes",
+            Statistic::InFlight => "number of IOs issued to the device but not completed",
+            Statistic::IoTicks => "time, in nanoseconds, where IOs were in flight",
+            Statistic::TimeInQueue => {
+                "time, in nanoseconds, spent by IOs in flight, weighted by the number in flight"
+            }
+        }
+    }
+
+    fn unit(&self) -> Option<Unit> {
+        match self {
+            Statistic::BandwidthRead | Statistic::BandwidthWrite => Some(Unit::Bytes),
+            Statistic::TimeRead
+            | Statistic::TimeWrite
+            | Statistic::IoTicks
+            | Statistic::TimeInQueue => Some(Unit::Nanoseconds),
+            _ => None,
+        }
+    }
+}
+
+impl Statistic {
+    /// The maximum secondly rate, or value for gauges, which is tracked
+    fn max(&self) -> u64 {
+        match self {
+            Statistic::BandwidthRead
+            | Statistic::BandwidthWrite
+            | Statistic::TimeRead
+            | Statistic::TimeWrite
+            | Statistic::IoTicks
+            | Statistic::TimeInQueue => TRILLION,
+            Statistic::InFlight => MILLION,
+            _ => BILLION,
+        }
     }
 
-    /// identifies the set of all primary block `Device`s on the host
-    fn get_devices(&self) -> Vec<Device> {
-        let re = Regex::new(r"^[a-z]+$").unwrap();
+    fn is_gauge(&self) -> bool {
+        *self == Statistic::InFlight
+    }
+}
+
+impl Disk {
+    /// send readings to the stats library. `previous` is the reading and
+    /// time of the last sample, from which the derived statistics are
+    /// computed for the given number of `devices`
+    fn record(
+        &self,
+        time: u64,
+        device: &Device,
+        reading: &Entry,
+        previous: Option<&(u64, Entry)>,
+        devices: u64,
+    ) {
+        for statistic in &self.statistics {
+            let value = reading.get(statistic);
+            if statistic.is_gauge() {
+                record_gauge(&self.recorder, labeled(statistic, device), time, value);
+            } else {
+                record_counter(&self.recorder, labeled(statistic, device), time, value);
+            }
+        }
+        // the derived statistics are skipped for an interval in which any
+        // counter went backwards
+        if let Some((previous_time, previous)) = previous {
+            if let Some(delta) = reading.delta(previous) {
+                let elapsed = time - previous_time;
+                for statistic in &self.derived_statistics {
+                    let value = statistic.compute(&delta, elapsed, devices);
+                    record_gauge(&self.recorder, labeled(statistic, device), time, value);
+                }
+            }
+        }
+    }
+
+    /// register the channels of each statistic for the `Device`
+    fn register_device(&self, device: &Device) {
+        let window = self.config.general().window();
+        for statistic in &self.statistics {
+            if statistic.is_gauge() {
+                register_gauge(
+                    &self.recorder,
+                    labeled(statistic, device),
+                    statistic.max(),
+                    3,
+                    window,
+                    PERCENTILES,
+                );
+            } else {
+                register_counter(
+                    &self.recorder,
+                    labeled(statistic, device),
+                    statistic.max(),
+                    3,
+                    window,
+                    PERCENTILES,
+                );
+            }
+        }
+        for statistic in &self.derived_statistics {
+            register_gauge(
+                &self.recorder,
+                labeled(statistic, device),
+                statistic.max(),
+                3,
+                window,
+                PERCENTILES,
+            );
+        }
+    }
+
+    fn deregister_device(&self, device: &Device) {
+        for statistic in &self.statistics {
+            delete_channel(&self.recorder, labeled(statistic, device).to_string());
+        }
+        for statistic in &self.derived_statistics {
+            delete_channel(&self.recorder, labeled(statistic, device).to_string());
+        }
+    }
+
+    /// register channels for devices which have appeared and remove those
+    /// of devices which are gone
+    fn refresh_registered(&mut self) {
+        let devices: HashSet<Device> = if self.config.disk().per_device() {
+            self.devices
+                .iter()
+                .map(|(device, _)| device.clone())
+                .collect()
+        } else {
+            HashSet::new()
+        };
+        for device in self.registered.difference(&devices) {
+            self.deregister_device(device);
+        }
+        for device in devices.difference(&self.registered) {
+            self.register_device(device);
+        }
+        self.registered = devices;
+    }
+
+    /// identifies the block `Device`s on the host which are of the
+    /// configured types and match the include and exclude patterns
+    fn get_devices(&self) -> Vec<(Device, DeviceType)> {
+        let sys_root = self.config.general().sys_root();
+        let device_types = self.config.disk().device_types();
         let mut result = Vec::new();
-        for entry in WalkDir::new("/sys/class/block/").max_depth(1) {
-            if let Ok(entry) = entry {
-                if let Some(s) = entry.file_name().to_str() {
-                    if s != "block" && re.is_match(s) {
-                        trace!("Found block dev: {}", s);
-                        result.push(Device::new(Some(s.to_owned())));
-                    } else {
-                        trace!("Ignore block dev: {}", s);
+        for entry in WalkDir::new(sys_root.join("class/block"))
+            .min_depth(1)
+            .max_depth(1)
+            .into_iter()
+            .filter_map(std::result::Result::ok)
+        {
+            if let Some(name) = entry.file_name().to_str() {
+                let device_type = match DeviceType::of(sys_root, name) {
+                    Some(device_type) => device_type,
+                    None => {
+                        trace!("Ignore block dev: unknown type: {}", name);
+                        continue;
                     }
+                };
+                if !device_types.contains(&device_type) {
+                    trace!("Ignore block dev: {:?}: {}", device_type, name);
+                } else if !self.filter.matches(name) {
+                    trace!("Ignore block dev: filtered: {}", name);
+                } else {
+                    trace!("Found block dev: {}", name);
+                    result.push((Device::new(Some(name.to_owned())), device_type));
                 }
             }
         }
+        result.sort_by_key(|(device, _)| device.name());
         result
     }
 }
 
-impl<'a> Sampler<'a> for Disk<'a> {
-    fn new(
-        config: &'a Config,
-        recorder: &'a Recorder<AtomicU32>,
-    ) -> Result<Option<Box<Self>>, Error> {
+impl Sampler for Disk {
+    fn new(config: Arc<Config>, recorder: Recorder<AtomicU32>) -> Result<Option<Box<Self>>, Error> {
         if config.disk().enabled() {
+            let filter = Filter::new(config.disk().include(), config.disk().exclude())
+                .map_err(|e| failure::format_err!("bad block device pattern: {}", e))?;
             Ok(Some(Box::new(Self {
                 config,
                 devices: Vec::new(),
+                filter,
                 initialized: false,
                 last_refreshed: 0,
                 recorder,
+                registered: HashSet::new(),
+                statistics: Vec::new(),
+                derived_statistics: Vec::new(),
+                previous: HashMap::new(),
             })))
         } else {
             Ok(None)
@@ -125,22 +310,60 @@ impl<'a> Sampler<'a> for Disk<'a> {
         "disk".to_string()
     }
 
-    fn sample(&mut self) -> Result<(), ()> {
+    fn sample(&mut self) -> Result<(), SamplerError> {
         trace!("sample {}", self.name());
         let time = time::precise_time_ns();
         let mut current = HashMap::new();
+        if !self.initialized {
+            self.register();
+        }
         if (time - self.last_refreshed) >= REFRESH {
-            self.devices = self.get_devices();
+            let devices = self.get_devices();
+            if devices != self.devices {
+                // the total is not comparable across a change in devices
+                self.previous.remove(&Device::new(None));
+            }
+            self.devices = devices;
             self.last_refreshed = time;
+            self.refresh_registered();
         }
-        for device in self.devices.clone() {
-            let entry = Entry::for_device(&device);
-            current.insert(device, entry);
+        for (device, _) in &self.devices {
+            let entry = Entry::for_device(self.config.general().sys_root(), device);
+            current.insert(device.clone(), entry);
         }
-        if !self.initialized {
-            self.register();
+        // the IOs of partitions and virtual devices are also counted by the
+        // disks beneath them, so the total only includes whole disks unless
+        // none are monitored
+        let whole: Vec<&Device> = self
+            .devices
+            .iter()
+            .filter(|(_, device_type)| *device_type == DeviceType::Disk)
+            .map(|(device, _)| device)
+            .collect();
+        let summed: Vec<&Device> = if whole.is_empty() {
+            self.devices.iter().map(|(device, _)| device).collect()
+        } else {
+            whole
+        };
+        let total = Device::new(None);
+        let sum: Entry = summed.iter().map(|device| &current[*device]).sum();
+        current.insert(total.clone(), sum);
+        self.record(
+            time,
+            &total,
+            &current[&total],
+            self.previous.get(&total),
+            summed.len() as u64,
+        );
+        for device in &self.registered {
+            if let Some(entry) = current.get(device) {
+                self.record(time, device, entry, self.previous.get(device), 1);
+            }
         }
-        self.record(time, self.recorder, current.values().sum());
+        self.previous = current
+            .into_iter()
+            .map(|(device, entry)| (device, (time, entry)))
+            .collect();
         Ok(())
     }
 
@@ -149,38 +372,10 @@ impl<'a> Sampler<'a> for Disk<'a> {
         if !self.initialized {
             self.devices = self.get_devices();
             self.last_refreshed = time::precise_time_ns();
-            register_counter(
-                self.recorder,
-                Statistic::BandwidthRead,
-                TRILLION,
-                3,
-                self.config.general().window(),
-                PERCENTILES,
-            );
-            register_counter(
-                self.recorder,
-                Statistic::BandwidthWrite,
-                TRILLION,
-                3,
-                self.config.general().window(),
-                PERCENTILES,
-            );
-            register_counter(
-                self.recorder,
-                Statistic::OperationsRead,
-                BILLION,
-                3,
-                self.config.general().window(),
-                PERCENTILES,
-            );
-            register_counter(
-                self.recorder,
-                Statistic::OperationsWrite,
-                BILLION,
-                3,
-                self.config.general().window(),
-                PERCENTILES,
-            );
+            self.statistics = self.config.disk().statistics();
+            self.derived_statistics = self.config.disk().derived_statistics();
+            self.register_device(&Device::new(None));
+            self.refresh_registered();
             self.initialized = true;
         }
     }
@@ -188,14 +383,12 @@ impl<'a> Sampler<'a> for Disk<'a> {
     fn deregister(&mut self) {
         trace!("deregister {}", self.name());
         if self.initialized {
-            self.recorder
-                .delete_channel(Statistic::BandwidthRead.to_string());
-            self.recorder
-                .delete_channel(Statistic::BandwidthWrite.to_string());
-            self.recorder
-                .delete_channel(Statistic::OperationsRead.to_string());
-            self.recorder
-                .delete_channel(Statistic::OperationsWrite.to_string());
+            self.deregister_device(&Device::new(None));
+            for device in &self.registered {
+                self.deregister_device(device);
+            }
+            self.registered.clear();
+            self.previous.clear();
             self.initialized = false;
         }
     }
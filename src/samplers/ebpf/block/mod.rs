@@ -4,8 +4,8 @@
 
 use crate::common::{BILLION, MICROSECOND, MILLION, PERCENTILES};
 use crate::config::Config;
-use crate::samplers::Sampler;
-use crate::stats::{record_distribution, register_distribution};
+use crate::samplers::{construct, Registration, Sampler, SamplerError};
+use crate::stats::{delete_distribution, record_distribution, register_distribution, Metric, Unit};
 
 use bcc;
 use bcc::core::BPF;
@@ -15,15 +15,25 @@ use metrics::*;
 use time;
 
 use std::collections::HashMap;
-
-pub struct Block<'a> {
-    config: &'a Config,
+use std::sync::Arc;
+
+pub const REGISTRATION: Registration = Registration {
+    name: "ebpf::block",
+    section: "ebpf",
+    config: |config| config.ebpf(),
+    changed: |old, new| old.ebpf() != new.ebpf(),
+    enabled: |config| config.ebpf().block(),
+    new: construct::<Block>,
+};
+
+pub struct Block {
+    config: Arc<Config>,
     bpf: BPF,
     initialized: bool,
-    recorder: &'a Recorder<AtomicU32>,
+    recorder: Recorder<AtomicU32>,
 }
 
-impl<'a> Block<'a> {
+impl Block {
     fn report_latency(&mut self, table: &str, label: &str) {
         let time = time::precise_time_ns();
         let mut current = HashMap::new();
@@ -49,7 +59,7 @@ impl<'a> Block<'a> {
             self.register();
         } else {
             for (&value, &count) in &current {
-                record_distribution(self.recorder, label, time, value, count);
+                record_distribution(&self.recorder, label, time, value, count);
             }
         }
     }
@@ -79,17 +89,17 @@ impl<'a> Block<'a> {
             self.register();
         } else {
             for (&value, &count) in &current {
-                record_distribution(self.recorder, label, time, value, count);
+                record_distribution(&self.recorder, label, time, value, count);
             }
         }
     }
 }
 
-impl<'a> Sampler<'a> for Block<'a> {
-    fn new(
-        config: &'a Config,
-        recorder: &'a Recorder<AtomicU32>,
-    ) -> Result<Option<Box<Self>>, Error> {
+impl Sampler for Block {
+    fn new(config: Arc<Config>, recorder: Recorder<AtomicU32>) -> Result<Option<Box<Self>>, Error> {
+        if !config.ebpf().block() {
+            return Ok(None);
+        }
         debug!("initializing");
         // load the code and compile
         let code = include_str!("bpf.c");
@@ -117,7 +127,7 @@ impl<'a> Sampler<'a> for Block<'a> {
         "ebpf::block".to_string()
     }
 
-    fn sample(&mut self) -> Result<(), ()> {
+    fn sample(&mut self) -> Result<(), SamplerError> {
         // gather current state
         trace!("sampling {}", self.name());
         self.report_size("read_size", "block/size/read");
@@ -136,8 +146,8 @@ impl<'a> Sampler<'a> for Block<'a> {
         if !self.initialized {
             for size in &["block/size/read", "block/size/write"] {
                 register_distribution(
-                    self.recorder,
-                    size,
+                    &self.recorder,
+                    Metric::new(size, "distribution of block IO sizes in kilobytes"),
                     MILLION,
                     2,
                     self.config.general().window(),
@@ -153,8 +163,9 @@ impl<'a> Sampler<'a> for Block<'a> {
                 "block/queue_latency/write",
             ] {
                 register_distribution(
-                    self.recorder,
-                    latency,
+                    &self.recorder,
+                    Metric::new(latency, "distribution of block IO latency in nanoseconds")
+                        .with_unit(Unit::Nanoseconds),
                     BILLION,
                     2,
                     self.config.general().window(),
@@ -168,20 +179,14 @@ impl<'a> Sampler<'a> for Block<'a> {
     fn deregister(&mut self) {
         debug!("deregister {}", self.name());
         if self.initialized {
-            self.recorder.delete_channel("block/size/read".to_string());
-            self.recorder.delete_channel("block/size/write".to_string());
-            self.recorder
-                .delete_channel("block/latency/read".to_string());
-            self.recorder
-                .delete_channel("block/device_latency/read".to_string());
-            self.recorder
-                .delete_channel("block/queue_latency/read".to_string());
-            self.recorder
-                .delete_channel("block/latency/write".to_string());
-            self.recorder
-                .delete_channel("block/device_latency/write".to_string());
-            self.recorder
-                .delete_channel("block/queue_latency/write".to_string());
+            delete_distribution(&self.recorder, "block/size/read");
+            delete_distribution(&self.recorder, "block/size/write");
+            delete_distribution(&self.recorder, "block/latency/read");
+            delete_distribution(&self.recorder, "block/device_latency/read");
+            delete_distribution(&self.recorder, "block/queue_latency/read");
+            delete_distribution(&self.recorder, "block/latency/write");
+            delete_distribution(&self.recorder, "block/device_latency/write");
+            delete_distribution(&self.recorder, "block/queue_latency/write");
             self.initialized = false;
         }
     }
@@ -4,8 +4,8 @@
 
 use crate::common::{MICROSECOND, PERCENTILES, SECOND};
 use crate::config::Config;
-use crate::samplers::Sampler;
-use crate::stats::{record_distribution, register_distribution};
+use crate::samplers::{construct, Registration, Sampler, SamplerError};
+use crate::stats::{delete_distribution, record_distribution, register_distribution, Metric, Unit};
 
 use bcc;
 use bcc::core::BPF;
@@ -16,23 +16,35 @@ use metrics::*;
 use time;
 
 use std::collections::HashMap;
-
-pub struct Ext4<'a> {
+use std::sync::Arc;
+
+pub const REGISTRATION: Registration = Registration {
+    name: "ebpf::ext4",
+    section: "ebpf",
+    config: |config| config.ebpf(),
+    changed: |old, new| old.ebpf() != new.ebpf(),
+    enabled: |config| config.ebpf().ext4(),
+    new: construct::<Ext4>,
+};
+
+pub struct Ext4 {
     bpf: BPF,
-    config: &'a Config,
+    config: Arc<Config>,
     initialized: bool,
-    recorder: &'a Recorder<AtomicU32>,
+    recorder: Recorder<AtomicU32>,
 }
 
-impl<'a> Sampler<'a> for Ext4<'a> {
-    fn new(
-        config: &'a Config,
-        recorder: &'a Recorder<AtomicU32>,
-    ) -> Result<Option<Box<Self>>, Error> {
+impl Sampler for Ext4 {
+    fn new(config: Arc<Config>, recorder: Recorder<AtomicU32>) -> Result<Option<Box<Self>>, Error> {
+        if !config.ebpf().ext4() {
+            return Ok(None);
+        }
         debug!("initializing");
         // load the code and compile
         let code = include_str!("bpf.c").to_string();
-        let addr = "0x".to_string() + &super::symbol_lookup("ext4_file_operations").unwrap();
+        let addr = "0x".to_string()
+            + &super::symbol_lookup(config.general().proc_root(), "ext4_file_operations")
+                .ok_or_else(|| format_err!("failed to find symbol: ext4_file_operations"))?;
         let code = code.replace("EXT4_FILE_OPERATIONS", &addr);
         let mut bpf = BPF::new(&code)?;
 
@@ -68,7 +80,7 @@ impl<'a> Sampler<'a> for Ext4<'a> {
         "ebpf::ext4".to_string()
     }
 
-    fn sample(&mut self) -> Result<(), ()> {
+    fn sample(&mut self) -> Result<(), SamplerError> {
         // gather current state
         trace!("sampling ebpf::ext4");
         let time = time::precise_time_ns();
@@ -81,7 +93,7 @@ impl<'a> Sampler<'a> for Ext4<'a> {
             let mut table = self.bpf.table(stat);
             for (&latency, &count) in &map_from_table(&mut table) {
                 record_distribution(
-                    self.recorder,
+                    &self.recorder,
                     format!("ext4/{}", stat),
                     time,
                     latency,
@@ -95,10 +107,17 @@ impl<'a> Sampler<'a> for Ext4<'a> {
     fn register(&mut self) {
         debug!("register {}", self.name());
         if !self.initialized {
-            for label in ["ext4/read", "ext4/write", "ext4/open", "ext4/fsync"].iter() {
+            for operation in &["read", "write", "open", "fsync"] {
                 register_distribution(
-                    self.recorder,
-                    label,
+                    &self.recorder,
+                    Metric::new(
+                        format!("ext4/{}", operation),
+                        format!(
+                            "distribution of latency for {} operations in nanoseconds",
+                            operation
+                        ),
+                    )
+                    .with_unit(Unit::Nanoseconds),
                     SECOND,
                     2,
                     self.config.general().window(),
@@ -113,7 +132,7 @@ impl<'a> Sampler<'a> for Ext4<'a> {
         debug!("deregister {}", self.name());
         if self.initialized {
             for label in ["ext4/read", "ext4/write", "ext4/open", "ext4/fsync"].iter() {
-                self.recorder.delete_channel(label.to_string());
+                delete_distribution(&self.recorder, label);
             }
             self.initialized = false;
         }
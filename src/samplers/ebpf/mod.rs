@@ -2,19 +2,15 @@
 // Licensed under the Apache License, Version 2.0
 // http://www.apache.org/licenses/LICENSE-2.0
 
-mod block;
-mod ext4;
-mod scheduler;
-mod xfs;
-
-pub use self::block::Block;
-pub use self::ext4::Ext4;
-pub use self::scheduler::Scheduler;
-pub use self::xfs::Xfs;
+pub(crate) mod block;
+pub(crate) mod ext4;
+pub(crate) mod scheduler;
+pub(crate) mod xfs;
 
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
+use std::path::Path;
 
 pub fn key_to_value(index: u64) -> Option<u64> {
     let index = index;
@@ -34,16 +30,11 @@ pub fn key_to_value(index: u64) -> Option<u64> {
 }
 
 // TODO: a result is probably more appropriate
-pub fn symbol_lookup(name: &str) -> Option<String> {
-    let symbols = File::open("/proc/kallsyms");
-    if symbols.is_err() {
-        return None;
-    }
-
-    let symbols = BufReader::new(symbols.unwrap());
+pub fn symbol_lookup(proc_root: &Path, name: &str) -> Option<String> {
+    let symbols = BufReader::new(File::open(proc_root.join("kallsyms")).ok()?);
 
     for line in symbols.lines() {
-        let line = line.unwrap();
+        let line = line.ok()?;
         let parts: Vec<&str> = line.split_whitespace().collect();
         if parts.get(2) == Some(&name) {
             return Some(parts[0].to_string());
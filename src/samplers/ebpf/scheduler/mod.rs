@@ -4,8 +4,8 @@
 
 use crate::common::{MICROSECOND, PERCENTILES, SECOND};
 use crate::config::Config;
-use crate::samplers::Sampler;
-use crate::stats::{record_distribution, register_distribution};
+use crate::samplers::{construct, Registration, Sampler, SamplerError};
+use crate::stats::{delete_distribution, record_distribution, register_distribution, Metric, Unit};
 
 use bcc;
 use bcc::core::BPF;
@@ -15,19 +15,29 @@ use metrics::*;
 use time;
 
 use std::collections::HashMap;
-
-pub struct Scheduler<'a> {
+use std::sync::Arc;
+
+pub const REGISTRATION: Registration = Registration {
+    name: "ebpf::scheduler",
+    section: "ebpf",
+    config: |config| config.ebpf(),
+    changed: |old, new| old.ebpf() != new.ebpf(),
+    enabled: |config| config.ebpf().scheduler(),
+    new: construct::<Scheduler>,
+};
+
+pub struct Scheduler {
     bpf: BPF,
-    config: &'a Config,
+    config: Arc<Config>,
     initialized: bool,
-    recorder: &'a Recorder<AtomicU32>,
+    recorder: Recorder<AtomicU32>,
 }
 
-impl<'a> Sampler<'a> for Scheduler<'a> {
-    fn new(
-        config: &'a Config,
-        recorder: &'a Recorder<AtomicU32>,
-    ) -> Result<Option<Box<Self>>, Error> {
+impl Sampler for Scheduler {
+    fn new(config: Arc<Config>, recorder: Recorder<AtomicU32>) -> Result<Option<Box<Self>>, Error> {
+        if !config.ebpf().scheduler() {
+            return Ok(None);
+        }
         debug!("initializing");
         // load the code and compile
         let code = include_str!("bpf.c");
@@ -54,7 +64,7 @@ impl<'a> Sampler<'a> for Scheduler<'a> {
         "ebpf::scheduler".to_string()
     }
 
-    fn sample(&mut self) -> Result<(), ()> {
+    fn sample(&mut self) -> Result<(), SamplerError> {
         // gather current state
         trace!("sampling {}", self.name());
         let time = time::precise_time_ns();
@@ -86,7 +96,7 @@ impl<'a> Sampler<'a> for Scheduler<'a> {
         } else {
             for (&latency, &count) in &current {
                 record_distribution(
-                    self.recorder,
+                    &self.recorder,
                     "scheduler/runqueue_latency_ns",
                     time,
                     latency,
@@ -102,8 +112,12 @@ impl<'a> Sampler<'a> for Scheduler<'a> {
         debug!("register {}", self.name());
         if !self.initialized {
             register_distribution(
-                self.recorder,
-                "scheduler/runqueue_latency_ns",
+                &self.recorder,
+                Metric::new(
+                    "scheduler/runqueue_latency_ns",
+                    "distribution of the time, in nanoseconds, runnable tasks wait to be scheduled",
+                )
+                .with_unit(Unit::Nanoseconds),
                 SECOND,
                 2,
                 self.config.general().window(),
@@ -116,8 +130,7 @@ impl<'a> Sampler<'a> for Scheduler<'a> {
     fn deregister(&mut self) {
         debug!("deregister {}", self.name());
         if self.initialized {
-            self.recorder
-                .delete_channel("scheduler/runqueue_latency_ns".to_string());
+            delete_distribution(&self.recorder, "scheduler/runqueue_latency_ns");
             self.initialized = false;
         }
     }
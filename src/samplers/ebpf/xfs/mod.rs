@@ -4,8 +4,8 @@
 
 use crate::common::{MICROSECOND, PERCENTILES, SECOND};
 use crate::config::Config;
-use crate::samplers::Sampler;
-use crate::stats::{record_distribution, register_distribution};
+use crate::samplers::{construct, Registration, Sampler, SamplerError};
+use crate::stats::{delete_distribution, record_distribution, register_distribution, Metric, Unit};
 
 use bcc;
 use bcc::core::BPF;
@@ -16,19 +16,29 @@ use metrics::*;
 use time;
 
 use std::collections::HashMap;
-
-pub struct Xfs<'a> {
+use std::sync::Arc;
+
+pub const REGISTRATION: Registration = Registration {
+    name: "ebpf::xfs",
+    section: "ebpf",
+    config: |config| config.ebpf(),
+    changed: |old, new| old.ebpf() != new.ebpf(),
+    enabled: |config| config.ebpf().xfs(),
+    new: construct::<Xfs>,
+};
+
+pub struct Xfs {
     bpf: BPF,
-    config: &'a Config,
+    config: Arc<Config>,
     initialized: bool,
-    recorder: &'a Recorder<AtomicU32>,
+    recorder: Recorder<AtomicU32>,
 }
 
-impl<'a> Sampler<'a> for Xfs<'a> {
-    fn new(
-        config: &'a Config,
-        recorder: &'a Recorder<AtomicU32>,
-    ) -> Result<Option<Box<Self>>, Error> {
+impl Sampler for Xfs {
+    fn new(config: Arc<Config>, recorder: Recorder<AtomicU32>) -> Result<Option<Box<Self>>, Error> {
+        if !config.ebpf().xfs() {
+            return Ok(None);
+        }
         debug!("initializing");
         // load the code and compile
         let code = include_str!("bpf.c").to_string();
@@ -65,7 +75,7 @@ impl<'a> Sampler<'a> for Xfs<'a> {
         "ebpf::xfs".to_string()
     }
 
-    fn sample(&mut self) -> Result<(), ()> {
+    fn sample(&mut self) -> Result<(), SamplerError> {
         // gather current state
         trace!("sample {}", self.name());
         let time = time::precise_time_ns();
@@ -76,7 +86,13 @@ impl<'a> Sampler<'a> for Xfs<'a> {
         for stat in &["read", "write", "open", "fsync"] {
             let mut table = self.bpf.table(stat);
             for (&latency, &count) in &map_from_table(&mut table) {
-                record_distribution(self.recorder, format!("xfs/{}", stat), time, latency, count);
+                record_distribution(
+                    &self.recorder,
+                    format!("xfs/{}", stat),
+                    time,
+                    latency,
+                    count,
+                );
             }
         }
         Ok(())
@@ -85,10 +101,17 @@ impl<'a> Sampler<'a> for Xfs<'a> {
     fn register(&mut self) {
         debug!("register {}", self.name());
         if !self.initialized {
-            for label in ["xfs/read", "xfs/write", "xfs/open", "xfs/fsync"].iter() {
+            for operation in &["read", "write", "open", "fsync"] {
                 register_distribution(
-                    self.recorder,
-                    label,
+                    &self.recorder,
+                    Metric::new(
+                        format!("xfs/{}", operation),
+                        format!(
+                            "distribution of latency for {} operations in nanoseconds",
+                            operation
+                        ),
+                    )
+                    .with_unit(Unit::Nanoseconds),
                     SECOND,
                     2,
                     self.config.general().window(),
@@ -103,7 +126,7 @@ impl<'a> Sampler<'a> for Xfs<'a> {
         debug!("deregister {}", self.name());
         if self.initialized {
             for label in ["xfs/read", "xfs/write", "xfs/open", "xfs/fsync"].iter() {
-                self.recorder.delete_channel(label.to_string());
+                delete_distribution(&self.recorder, label);
             }
             self.initialized = false;
         }
@@ -0,0 +1,48 @@
+// Copyright 2019 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::fmt;
+
+/// Errors returned by `Sampler::sample()`. The message carries the context
+/// of the failure, such as the source which could not be read.
+#[derive(Debug)]
+pub enum SamplerError {
+    /// The sample could not be taken, but the next one may succeed. For
+    /// example, a file in `/proc` could not be read. The sampler continues on
+    /// its normal cadence.
+    Transient(String),
+    /// The sampler can not continue, for example its connection has been
+    /// closed. The sampler is failed and constructed again after a backoff.
+    Fatal(String),
+}
+
+impl SamplerError {
+    /// The kind of error, used to label the error counts for a sampler
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SamplerError::Transient(_) => "transient",
+            SamplerError::Fatal(_) => "fatal",
+        }
+    }
+
+    /// Returns true if the sampler can not continue
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            SamplerError::Transient(_) => false,
+            SamplerError::Fatal(_) => true,
+        }
+    }
+}
+
+impl fmt::Display for SamplerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SamplerError::Transient(context) | SamplerError::Fatal(context) => {
+                write!(f, "{} error: {}", self.kind(), context)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SamplerError {}
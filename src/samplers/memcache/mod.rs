@@ -4,47 +4,63 @@
 
 use crate::common::*;
 use crate::config::Config;
-use crate::samplers::Sampler;
-use crate::stats::{record_counter, record_gauge, register_counter};
+use crate::samplers::{construct, Registration, Sampler, SamplerError};
+use crate::stats::{
+    delete_channel, record_counter, record_gauge, register_counter, register_value, Metric,
+};
 use failure::Error;
 use logger::*;
 use metrics::*;
 use std::io::{Read, Write};
 use std::net::{TcpStream, ToSocketAddrs};
-use std::process;
-use std::str;
+use std::sync::Arc;
+use std::time::Duration;
 use time;
 
-pub struct Memcache<'a> {
-    config: &'a Config,
+pub const REGISTRATION: Registration = Registration {
+    name: "memcache",
+    section: "memcache",
+    config: |config| config.memcache(),
+    changed: |old, new| old.memcache() != new.memcache(),
+    enabled: |config| config.memcache().endpoint().is_some(),
+    new: construct::<Memcache>,
+};
+
+pub struct Memcache {
+    config: Arc<Config>,
     stream: TcpStream,
     initialized: bool,
-    recorder: &'a Recorder<AtomicU32>,
+    recorder: Recorder<AtomicU32>,
+    channels: Vec<String>,
 }
 
-impl<'a> Sampler<'a> for Memcache<'a> {
-    fn new(
-        config: &'a Config,
-        recorder: &'a Recorder<AtomicU32>,
-    ) -> Result<Option<Box<Self>>, Error> {
-        let endpoint = config.memcache().unwrap();
-        let mut addrs = endpoint.to_socket_addrs().unwrap_or_else(|_| {
-            println!("ERROR: endpoint address is malformed: {}", endpoint);
-            std::process::exit(1);
-        });
-        let sock_addr = addrs.next().unwrap_or_else(|| {
-            println!("ERROR: failed to resolve address: {}", endpoint);
-            std::process::exit(1);
-        });
-        let stream = TcpStream::connect(sock_addr).unwrap_or_else(|e| {
-            error!("Failed to connect to memcache: {}", e);
-            process::exit(1);
-        });
+impl Sampler for Memcache {
+    fn new(config: Arc<Config>, recorder: Recorder<AtomicU32>) -> Result<Option<Box<Self>>, Error> {
+        let endpoint = match config.memcache().endpoint() {
+            Some(endpoint) => endpoint,
+            None => return Ok(None),
+        };
+        let sock_addr = endpoint
+            .to_socket_addrs()
+            .map_err(|e| {
+                failure::format_err!("endpoint address is malformed: {}: {}", endpoint, e)
+            })?
+            .next()
+            .ok_or_else(|| failure::format_err!("failed to resolve address: {}", endpoint))?;
+        // a hung memcache must not block the worker past the timeout
+        let timeout = Duration::from_millis(config.general().timeout().max(1) as u64);
+        let stream = TcpStream::connect_timeout(&sock_addr, timeout)
+            .map_err(|e| failure::format_err!("failed to connect to memcache: {}", e))?;
+        stream
+            .set_read_timeout(Some(timeout))
+            .and_then(|_| stream.set_write_timeout(Some(timeout)))
+            .map_err(|e| failure::format_err!("failed to set socket timeout: {}", e))?;
         Ok(Some(Box::new(Memcache {
             config,
             stream,
             initialized: false,
             recorder,
+            channels: Vec::new(),
         })))
     }
 
@@ -52,58 +68,84 @@ impl<'a> Sampler<'a> for Memcache<'a> {
         "memcache".to_string()
     }
 
-    fn sample(&mut self) -> Result<(), ()> {
+    fn sample(&mut self) -> Result<(), SamplerError> {
         // gather current state
         trace!("sampling memcache");
         let time = time::precise_time_ns();
-        self.stream.write_all(b"stats\r\n").unwrap();
+        self.stream
+            .write_all(b"stats\r\n")
+            .map_err(|e| io_error("failed to send stats command", e))?;
         let mut buffer = [0_u8; 16355];
+        let deadline = time + self.config.general().timeout() as u64 * MILLISECOND;
         loop {
-            let length = self.stream.peek(&mut buffer).unwrap();
-            if length > 0 {
-                let stats = str::from_utf8(&buffer).unwrap().to_string();
-                let lines: Vec<&str> = stats.split("\r\n").collect();
-                if lines[lines.len() - 2] == "END" {
-                    break;
-                }
+            let length = self
+                .stream
+                .peek(&mut buffer)
+                .map_err(|e| io_error("failed to read stats", e))?;
+            if length == 0 {
+                return Err(SamplerError::Fatal("connection closed".to_string()));
+            }
+            let stats = String::from_utf8_lossy(&buffer[..length]);
+            let lines: Vec<&str> = stats.split("\r\n").collect();
+            if lines.len() >= 2 && lines[lines.len() - 2] == "END" {
+                break;
             }
+            // peek returns immediately while a partial response is buffered,
+            // so wait briefly for the rest to arrive
+            if time::precise_time_ns() > deadline {
+                return Err(SamplerError::Fatal(
+                    "timed out waiting for the end of stats".to_string(),
+                ));
+            }
+            std::thread::sleep(Duration::from_millis(1));
         }
-        let length = self.stream.read(&mut buffer).unwrap();
+        let length = self
+            .stream
+            .read(&mut buffer)
+            .map_err(|e| io_error("failed to read stats", e))?;
         if length > 0 {
-            let stats = str::from_utf8(&buffer).unwrap().to_string();
+            let stats = String::from_utf8_lossy(&buffer[..length]).to_string();
             let lines: Vec<&str> = stats.split("\r\n").collect();
             for line in lines {
                 let parts: Vec<&str> = line.split_whitespace().collect();
                 if let Some(name) = parts.get(1) {
                     if let Some(value) = parts.get(2) {
+                        let label = self.label(name);
                         match *name {
                             "data_read" | "data_written" | "cmd_total" | "conn_total"
                             | "conn_yield" | "hotkey_bw" | "hotkey_qps" => {
                                 if !self.initialized {
                                     register_counter(
-                                        self.recorder,
-                                        name,
+                                        &self.recorder,
+                                        Metric::new(
+                                            &label,
+                                            format!("{} as reported by memcache", name),
+                                        ),
                                         BILLION,
                                         3,
                                         self.config.general().window(),
                                         PERCENTILES,
                                     );
+                                    self.channels.push(label.clone());
                                 }
                                 if let Ok(value) = value.parse::<f64>().map(|v| v.floor() as u64) {
-                                    record_counter(self.recorder, name.to_string(), time, value);
+                                    record_counter(&self.recorder, label, time, value);
                                 }
                             }
                             _ => {
                                 if !self.initialized {
-                                    self.recorder.add_channel(
-                                        name.to_string(),
+                                    register_value(
+                                        &self.recorder,
+                                        Metric::new(
+                                            &label,
+                                            format!("{} as reported by memcache", name),
+                                        ),
                                         Source::Gauge,
-                                        None,
                                     );
-                                    self.recorder.add_output(name.to_string(), Output::Counter);
+                                    self.channels.push(label.clone());
                                 }
                                 if let Ok(value) = value.parse::<f64>().map(|v| v.floor() as u64) {
-                                    record_gauge(self.recorder, name.to_string(), time, value);
+                                    record_gauge(&self.recorder, label, time, value);
                                 }
                             }
                         }
@@ -111,11 +153,10 @@ impl<'a> Sampler<'a> for Memcache<'a> {
                 }
             }
             self.initialized = true;
+            Ok(())
         } else {
-            error!("failed to get stats");
-            std::process::exit(1);
+            Err(SamplerError::Fatal("connection closed".to_string()))
         }
-        Ok(())
     }
 
     fn register(&mut self) {
@@ -123,6 +164,28 @@ impl<'a> Sampler<'a> for Memcache<'a> {
     }
 
     fn deregister(&mut self) {
-        // this is not implemented
+        for label in self.channels.drain(..) {
+            delete_channel(&self.recorder, label);
+        }
+        self.initialized = false;
     }
 }
+
+impl Memcache {
+    /// stats are namespaced under `memcache/` unless running in passthrough
+    /// mode, where they keep the names used by memcache
+    fn label(&self, name: &str) -> String {
+        if self.config.general().passthrough() {
+            name.to_string()
+        } else {
+            format!("memcache/{}", name)
+        }
+    }
+}
+
+/// Any failed read or write, including one which timed out, replaces the
+/// connection. A late response would otherwise be left in the socket and
+/// parsed as the reply to the next sample
+fn io_error(context: &str, e: std::io::Error) -> SamplerError {
+    SamplerError::Fatal(format!("{}: {}", context, e))
+}
@@ -0,0 +1,361 @@
+// Copyright 2019 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use crate::common::*;
+use crate::config::Config;
+use crate::samplers::Statistic as _;
+use crate::samplers::{construct, Registration, Sampler, SamplerError};
+use crate::stats::{delete_channel, record_gauge, register_gauge, Unit};
+use failure::Error;
+
+use logger::*;
+use metrics::*;
+use serde_derive::*;
+use time;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+const MEMINFO: &str = "meminfo";
+
+pub const REGISTRATION: Registration = Registration {
+    name: "memory",
+    section: "memory",
+    config: |config| config.memory(),
+    changed: |old, new| old.memory() != new.memory(),
+    enabled: |config| config.memory().enabled(),
+    new: construct::<Memory>,
+};
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Hash)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub enum Statistic {
+    Total,
+    Free,
+    Available,
+    Buffers,
+    Cached,
+    SwapCached,
+    Active,
+    Inactive,
+    ActiveAnon,
+    InactiveAnon,
+    ActiveFile,
+    InactiveFile,
+    Unevictable,
+    Mlocked,
+    SwapTotal,
+    SwapFree,
+    Dirty,
+    Writeback,
+    AnonPages,
+    Mapped,
+    Shmem,
+    Slab,
+    SlabReclaimable,
+    SlabUnreclaimable,
+    KernelStack,
+    PageTables,
+    CommitLimit,
+    CommittedAs,
+    VmallocUsed,
+    AnonHugePages,
+    HugePagesTotal,
+    HugePagesFree,
+    HugePagesReserved,
+    HugePagesSurplus,
+    HugePageSize,
+}
+
+impl std::fmt::Display for Statistic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Statistic::Total => write!(f, "memory/total"),
+            Statistic::Free => write!(f, "memory/free"),
+            Statistic::Available => write!(f, "memory/available"),
+            Statistic::Buffers => write!(f, "memory/buffers"),
+            Statistic::Cached => write!(f, "memory/cached"),
+            Statistic::SwapCached => write!(f, "memory/swap/cached"),
+            Statistic::Active => write!(f, "memory/active"),
+            Statistic::Inactive => write!(f, "memory/inactive"),
+            Statistic::ActiveAnon => write!(f, "memory/active/anon"),
+            Statistic::InactiveAnon => write!(f, "memory/inactive/anon"),
+            Statistic::ActiveFile => write!(f, "memory/active/file"),
+            Statistic::InactiveFile => write!(f, "memory/inactive/file"),
+            Statistic::Unevictable => write!(f, "memory/unevictable"),
+            Statistic::Mlocked => write!(f, "memory/mlocked"),
+            Statistic::SwapTotal => write!(f, "memory/swap/total"),
+            Statistic::SwapFree => write!(f, "memory/swap/free"),
+            Statistic::Dirty => write!(f, "memory/dirty"),
+            Statistic::Writeback => write!(f, "memory/writeback"),
+            Statistic::AnonPages => write!(f, "memory/anon_pages"),
+            Statistic::Mapped => write!(f, "memory/mapped"),
+            Statistic::Shmem => write!(f, "memory/shmem"),
+            Statistic::Slab => write!(f, "memory/slab/total"),
+            Statistic::SlabReclaimable => write!(f, "memory/slab/reclaimable"),
+            Statistic::SlabUnreclaimable => write!(f, "memory/slab/unreclaimable"),
+            Statistic::KernelStack => write!(f, "memory/kernel_stack"),
+            Statistic::PageTables => write!(f, "memory/page_tables"),
+            Statistic::CommitLimit => write!(f, "memory/commit/limit"),
+            Statistic::CommittedAs => write!(f, "memory/commit/committed"),
+            Statistic::VmallocUsed => write!(f, "memory/vmalloc_used"),
+            Statistic::AnonHugePages => write!(f, "memory/anon_huge_pages"),
+            Statistic::HugePagesTotal => write!(f, "memory/hugepages/total"),
+            Statistic::HugePagesFree => write!(f, "memory/hugepages/free"),
+            Statistic::HugePagesReserved => write!(f, "memory/hugepages/reserved"),
+            Statistic::HugePagesSurplus => write!(f, "memory/hugepages/surplus"),
+            Statistic::HugePageSize => write!(f, "memory/hugepages/size"),
+        }
+    }
+}
+
+impl crate::samplers::Statistic for Statistic {
+    fn description(&self) -> &str {
+        match self {
+            Statistic::Total => "amount of usable memory, in bytes",
+            Statistic::Free => "amount of memory, in bytes, which is unused",
+            Statistic::Available => {
+                "estimate of the memory, in bytes, available to start new applications without swapping"
+            }
+            Statistic::Buffers => "amount of memory, in bytes, used by block device buffers",
+            Statistic::Cached => "amount of memory, in bytes, used by the page cache",
+            Statistic::SwapCached => {
+                "amount of memory, in bytes, which was swapped out and is also in swap"
+            }
+            Statistic::Active => "amount of memory, in bytes, which was recently used",
+            Statistic::Inactive => {
+                "amount of memory, in bytes, which was not recently used and may be reclaimed"
+            }
+            Statistic::ActiveAnon => "amount of anonymous memory, in bytes, recently used",
+            Statistic::InactiveAnon => "amount of anonymous memory, in bytes, not recently used",
+            Statistic::ActiveFile => "amount of file-backed memory, in bytes, recently used",
+            Statistic::InactiveFile => "amount of file-backed memory, in bytes, not recently used",
+            Statistic::Unevictable => "amount of memory, in bytes, which cannot be reclaimed",
+            Statistic::Mlocked => "amount of memory, in bytes, locked with mlock()",
+            Statistic::SwapTotal => "amount of swap space, in bytes",
+            Statistic::SwapFree => "amount of swap space, in bytes, which is unused",
+            Statistic::Dirty => "amount of memory, in bytes, waiting to be written to disk",
+            Statistic::Writeback => "amount of memory, in bytes, being written to disk",
+            Statistic::AnonPages => "amount of memory, in bytes, in anonymous pages",
+            Statistic::Mapped => "amount of memory, in bytes, mapped into processes",
+            Statistic::Shmem => "amount of memory, in bytes, used by shared memory and tmpfs",
+            Statistic::Slab => "amount of memory, in bytes, used by kernel slab caches",
+            Statistic::SlabReclaimable => {
+                "amount of memory, in bytes, used by slab caches which may be reclaimed"
+            }
+            Statistic::SlabUnreclaimable => {
+                "amount of memory, in bytes, used by slab caches which cannot be reclaimed"
+            }
+            Statistic::KernelStack => "amount of memory, in bytes, used by kernel stacks",
+            Statistic::PageTables => "amount of memory, in bytes, used by page tables",
+            Statistic::CommitLimit => {
+                "amount of memory, in bytes, which may be allocated under strict overcommit"
+            }
+            Statistic::CommittedAs => "amount of memory, in bytes, which has been allocated",
+            Statistic::VmallocUsed => "amount of memory, in bytes, used by vmalloc",
+            Statistic::AnonHugePages => {
+                "amount of memory, in bytes, in anonymous transparent huge pages"
+            }
+            Statistic::HugePagesTotal => "number of huge pages in the pool",
+            Statistic::HugePagesFree => "number of huge pages in the pool which are unallocated",
+            Statistic::HugePagesReserved => {
+                "number of huge pages which are reserved but not yet allocated"
+            }
+            Statistic::HugePagesSurplus => "number of huge pages in excess of the pool size",
+            Statistic::HugePageSize => "size, in bytes, of a huge page",
+        }
+    }
+
+    fn unit(&self) -> Option<Unit> {
+        match self {
+            Statistic::HugePagesTotal
+            | Statistic::HugePagesFree
+            | Statistic::HugePagesReserved
+            | Statistic::HugePagesSurplus => None,
+            _ => Some(Unit::Bytes),
+        }
+    }
+}
+
+impl Statistic {
+    /// The name of the field in `/proc/meminfo`
+    fn key(&self) -> &'static str {
+        match self {
+            Statistic::Total => "MemTotal",
+            Statistic::Free => "MemFree",
+            Statistic::Available => "MemAvailable",
+            Statistic::Buffers => "Buffers",
+            Statistic::Cached => "Cached",
+            Statistic::SwapCached => "SwapCached",
+            Statistic::Active => "Active",
+            Statistic::Inactive => "Inactive",
+            Statistic::ActiveAnon => "Active(anon)",
+            Statistic::InactiveAnon => "Inactive(anon)",
+            Statistic::ActiveFile => "Active(file)",
+            Statistic::InactiveFile => "Inactive(file)",
+            Statistic::Unevictable => "Unevictable",
+            Statistic::Mlocked => "Mlocked",
+            Statistic::SwapTotal => "SwapTotal",
+            Statistic::SwapFree => "SwapFree",
+            Statistic::Dirty => "Dirty",
+            Statistic::Writeback => "Writeback",
+            Statistic::AnonPages => "AnonPages",
+            Statistic::Mapped => "Mapped",
+            Statistic::Shmem => "Shmem",
+            Statistic::Slab => "Slab",
+            Statistic::SlabReclaimable => "SReclaimable",
+            Statistic::SlabUnreclaimable => "SUnreclaim",
+            Statistic::KernelStack => "KernelStack",
+            Statistic::PageTables => "PageTables",
+            Statistic::CommitLimit => "CommitLimit",
+            Statistic::CommittedAs => "Committed_AS",
+            Statistic::VmallocUsed => "VmallocUsed",
+            Statistic::AnonHugePages => "AnonHugePages",
+            Statistic::HugePagesTotal => "HugePages_Total",
+            Statistic::HugePagesFree => "HugePages_Free",
+            Statistic::HugePagesReserved => "HugePages_Rsvd",
+            Statistic::HugePagesSurplus => "HugePages_Surp",
+            Statistic::HugePageSize => "Hugepagesize",
+        }
+    }
+
+    /// The maximum value which is tracked
+    fn max(&self) -> u64 {
+        match self.unit() {
+            Some(Unit::Bytes) => 64 * TERABYTE,
+            _ => BILLION,
+        }
+    }
+}
+
+/// Parses `/proc/meminfo` into the value of each field. Fields reported in
+/// kB are converted to bytes
+fn parse_meminfo<T: BufRead>(reader: &mut T) -> Result<HashMap<String, u64>, SamplerError> {
+    let mut result = HashMap::new();
+    for line in reader.lines() {
+        let line =
+            line.map_err(|e| SamplerError::Transient(format!("could not read meminfo: {}", e)))?;
+        let mut parts = line.split_whitespace();
+        let key = match parts.next().and_then(|key| key.strip_suffix(':')) {
+            Some(key) => key,
+            None => continue,
+        };
+        let value: u64 = match parts.next().map(str::parse) {
+            Some(Ok(value)) => value,
+            _ => continue,
+        };
+        let value = match parts.next() {
+            Some("kB") => value * 1024,
+            _ => value,
+        };
+        result.insert(key.to_string(), value);
+    }
+    Ok(result)
+}
+
+fn read_meminfo(proc_root: &Path) -> Result<HashMap<String, u64>, SamplerError> {
+    let path = proc_root.join(MEMINFO);
+    let file = File::open(&path).map_err(|e| {
+        SamplerError::Transient(format!("could not open {}: {}", path.display(), e))
+    })?;
+    parse_meminfo(&mut BufReader::new(file))
+}
+
+pub struct Memory {
+    config: Arc<Config>,
+    initialized: bool,
+    recorder: Recorder<AtomicU32>,
+    /// the statistics which were registered, as the config may be reloaded
+    statistics: Vec<Statistic>,
+}
+
+impl Sampler for Memory {
+    fn new(config: Arc<Config>, recorder: Recorder<AtomicU32>) -> Result<Option<Box<Self>>, Error> {
+        if config.memory().enabled() {
+            Ok(Some(Box::new(Self {
+                config,
+                initialized: false,
+                recorder,
+                statistics: Vec::new(),
+            })))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn name(&self) -> String {
+        "memory".to_string()
+    }
+
+    fn sample(&mut self) -> Result<(), SamplerError> {
+        trace!("sample {}", self.name());
+        let data = read_meminfo(self.config.general().proc_root())?;
+        let time = time::precise_time_ns();
+        if !self.initialized {
+            self.register();
+        }
+        for statistic in &self.statistics {
+            // fields vary by kernel version and configuration
+            if let Some(value) = data.get(statistic.key()) {
+                record_gauge(&self.recorder, statistic, time, *value);
+            }
+        }
+        Ok(())
+    }
+
+    fn register(&mut self) {
+        trace!("register {}", self.name());
+        if !self.initialized {
+            self.statistics = self.config.memory().statistics();
+            for statistic in &self.statistics {
+                register_gauge(
+                    &self.recorder,
+                    statistic,
+                    statistic.max(),
+                    3,
+                    self.config.general().window(),
+                    PERCENTILES,
+                );
+            }
+            self.initialized = true;
+        }
+    }
+
+    fn deregister(&mut self) {
+        trace!("deregister {}", self.name());
+        if self.initialized {
+            for statistic in &self.statistics {
+                delete_channel(&self.recorder, statistic.to_string());
+            }
+            self.initialized = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_meminfo_from_root() {
+        let data = read_meminfo(Path::new("tests/data/proc")).unwrap();
+        assert_eq!(data[Statistic::Total.key()], 16315440 * 1024);
+        assert_eq!(data[Statistic::Available.key()], 11874852 * 1024);
+        assert_eq!(data[Statistic::ActiveAnon.key()], 3307036 * 1024);
+        assert_eq!(data[Statistic::CommittedAs.key()], 11230204 * 1024);
+        assert_eq!(data[Statistic::HugePagesTotal.key()], 64);
+        assert_eq!(data[Statistic::HugePagesReserved.key()], 2);
+        assert_eq!(data[Statistic::HugePageSize.key()], 2048 * 1024);
+    }
+
+    #[test]
+    fn parse_meminfo_malformed() {
+        let data = parse_meminfo(&mut "\nMemTotal: 8 kB\nbogus\nMemFree:\n".as_bytes()).unwrap();
+        assert_eq!(data.len(), 1);
+        assert_eq!(data["MemTotal"], 8192);
+    }
+}
@@ -3,41 +3,41 @@
 // http://www.apache.org/licenses/LICENSE-2.0
 
 pub(crate) mod cpu;
+pub(crate) mod cpupower;
 pub(crate) mod disk;
 #[cfg(feature = "ebpf")]
 pub(crate) mod ebpf;
+mod error;
 pub(crate) mod memcache;
+pub(crate) mod memory;
 pub(crate) mod network;
 #[cfg(feature = "perf")]
 pub(crate) mod perf;
 pub(crate) mod rezolus;
 pub(crate) mod softnet;
 
-pub use self::cpu::Cpu;
-pub use self::disk::Disk;
-pub use self::memcache::Memcache;
-pub use self::network::Network;
-#[cfg(feature = "perf")]
-pub use self::perf::Perf;
-pub use self::rezolus::Rezolus;
-pub use self::softnet::Softnet;
+pub use self::error::SamplerError;
+
 use failure::Error;
 
-use crate::config::Config;
+use crate::config::{Config, SamplerConfig};
+use crate::stats::{Labeled, Unit};
 use metrics::{AtomicU32, Recorder};
 
+use std::sync::Arc;
+
 /// `Sampler`s are used to get samples of a particular subsystem or component
 /// The `Sampler` will send `Message`s across the `Sender` for aggregation by
 /// the stats library, `tock`
-pub trait Sampler<'a> {
-    fn new(
-        config: &'a Config,
-        recorder: &'a Recorder<AtomicU32>,
-    ) -> Result<Option<Box<Self>>, Error>
+///
+/// Each `Sampler` is constructed and driven on its own worker thread by the
+/// `Scheduler`, so it owns its handles to the `Config` and `Recorder`
+pub trait Sampler {
+    fn new(config: Arc<Config>, recorder: Recorder<AtomicU32>) -> Result<Option<Box<Self>>, Error>
     where
         Self: Sized;
     /// Perform required sampling steps and send stats to the `Recorder`
-    fn sample(&mut self) -> Result<(), ()>;
+    fn sample(&mut self) -> Result<(), SamplerError>;
 
     /// Return the name of the `Sampler`
     fn name(&self) -> String;
@@ -49,4 +49,96 @@ pub trait Sampler<'a> {
     fn deregister(&mut self);
 }
 
-pub trait Statistic: ToString + Sized {}
+/// A `Statistic` is a metric reported by a `Sampler`, the label of its
+/// channel is the `Display` of the statistic
+pub trait Statistic: ToString + Sized {
+    /// Help text for the statistic, used by the exposition formats
+    fn description(&self) -> &str;
+
+    /// The unit of the values recorded for the statistic, if it has one
+    fn unit(&self) -> Option<Unit> {
+        None
+    }
+
+    /// Attach a key/value label identifying the entity which is measured,
+    /// such as the device of a disk statistic
+    fn with_label<K: ToString, V: ToString>(self, key: K, value: V) -> Labeled<Self> {
+        Labeled::new(self).with_label(key, value)
+    }
+
+    /// Label the statistic for one of the entities which make up a total,
+    /// such as a single CPU or disk. The series is named within a
+    /// `per_(key)` scope, so that each metric family holds either the total
+    /// or the series of a single kind of entity, which may then be summed
+    fn per<V: ToString>(self, key: &str, value: V) -> Labeled<Self> {
+        Labeled::new(self)
+            .with_scope(format!("per_{}", key))
+            .with_label(key, value)
+    }
+}
+
+impl<T: Statistic + std::fmt::Display> Statistic for &T {
+    fn description(&self) -> &str {
+        (*self).description()
+    }
+
+    fn unit(&self) -> Option<Unit> {
+        (*self).unit()
+    }
+}
+
+type Constructor = fn(Arc<Config>, Recorder<AtomicU32>) -> Result<Option<Box<dyn Sampler>>, Error>;
+
+/// Each sampler module declares a `Registration` which describes the sampler
+/// to the `Scheduler`
+#[derive(Clone, Copy)]
+pub struct Registration {
+    /// unique name of the sampler
+    pub name: &'static str,
+    /// config section which configures the sampler
+    pub section: &'static str,
+    /// returns the config section, from which the interval between samples
+    /// is taken
+    pub config: fn(&Config) -> &dyn SamplerConfig,
+    /// returns true if the config section differs between the old and new
+    /// config, in which case the sampler is restarted on reload
+    pub changed: fn(&Config, &Config) -> bool,
+    /// returns true if the sampler is enabled by the config
+    pub enabled: fn(&Config) -> bool,
+    /// constructs the sampler
+    pub new: Constructor,
+}
+
+/// Construct a `Sampler` of type `T` as a trait object
+pub fn construct<T: Sampler + 'static>(
+    config: Arc<Config>,
+    recorder: Recorder<AtomicU32>,
+) -> Result<Option<Box<dyn Sampler>>, Error> {
+    Ok(T::new(config, recorder)?.map(|sampler| sampler as Box<dyn Sampler>))
+}
+
+/// Returns the `Registration` of every sampler built into Rezolus. New
+/// samplers are added by declaring a `REGISTRATION` in their module and
+/// listing it here
+pub fn registry() -> Vec<Registration> {
+    let mut registry = vec![
+        cpu::REGISTRATION,
+        cpupower::REGISTRATION,
+        disk::REGISTRATION,
+        rezolus::REGISTRATION,
+        memcache::REGISTRATION,
+        memory::REGISTRATION,
+        network::REGISTRATION,
+    ];
+    #[cfg(feature = "ebpf")]
+    registry.extend_from_slice(&[
+        ebpf::block::REGISTRATION,
+        ebpf::ext4::REGISTRATION,
+        ebpf::scheduler::REGISTRATION,
+        ebpf::xfs::REGISTRATION,
+    ]);
+    #[cfg(feature = "perf")]
+    registry.push(perf::REGISTRATION);
+    registry.push(softnet::REGISTRATION);
+    registry
+}
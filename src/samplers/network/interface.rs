@@ -4,17 +4,36 @@
 
 use crate::common::net;
 use crate::samplers::Statistic;
+use crate::stats::Unit;
 
 use serde_derive::*;
 
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+/// A network interface, identified by its name alone so that a change in its
+/// link speed does not make it a new interface
+#[derive(Clone, Debug)]
 pub struct Interface {
     name: Option<String>,
     bandwidth_bytes: Option<u64>,
 }
 
+impl PartialEq for Interface {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for Interface {}
+
+impl Hash for Interface {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+    }
+}
+
 impl fmt::Display for Interface {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.name {
@@ -40,9 +59,13 @@ impl Interface {
         self.bandwidth_bytes
     }
 
-    pub fn get_statistic(&self, statistic: &InterfaceStatistic) -> Result<u64, ()> {
+    pub fn get_statistic(
+        &self,
+        sys_root: &Path,
+        statistic: &InterfaceStatistic,
+    ) -> Result<u64, ()> {
         if let Some(name) = &self.name {
-            net::read_network_stat(&name, statistic.name())
+            net::read_network_stat(sys_root, name, statistic.name())
         } else {
             Err(())
         }
@@ -136,7 +159,41 @@ impl fmt::Display for InterfaceStatistic {
     }
 }
 
-impl Statistic for InterfaceStatistic {}
+impl Statistic for InterfaceStatistic {
+    fn description(&self) -> &str {
+        match self {
+            InterfaceStatistic::RxBytes => "number of bytes received",
+            InterfaceStatistic::RxCrcErrors => "number of packets received with CRC error",
+            InterfaceStatistic::RxDiscardsPhy => {
+                "number of packets dropped due to lack of buffer space on the NIC"
+            }
+            InterfaceStatistic::RxDropped => {
+                "number of packets dropped and not forwarded to the upper layers"
+            }
+            InterfaceStatistic::RxErrors => "number of errors on receive",
+            InterfaceStatistic::RxFifoErrors => "number of receive FIFO errors",
+            InterfaceStatistic::RxMissedErrors => {
+                "number of packets missed due to lack of capacity on receive"
+            }
+            InterfaceStatistic::RxPackets => "number of packets received",
+            InterfaceStatistic::TxBytes => "number of bytes transmitted",
+            InterfaceStatistic::TxDiscardsPhy => {
+                "number of packets dropped due to lack of buffers on transmit"
+            }
+            InterfaceStatistic::TxDropped => "number of packets dropped on transmit",
+            InterfaceStatistic::TxErrors => "number of errors on transmit",
+            InterfaceStatistic::TxFifoErrors => "number of transmit FIFO errors",
+            InterfaceStatistic::TxPackets => "number of packets transmitted",
+        }
+    }
+
+    fn unit(&self) -> Option<Unit> {
+        match self {
+            InterfaceStatistic::RxBytes | InterfaceStatistic::TxBytes => Some(Unit::Bytes),
+            _ => None,
+        }
+    }
+}
 
 impl InterfaceStatistic {
     pub fn name(&self) -> &str {
@@ -6,12 +6,15 @@ pub(crate) mod interface;
 pub(crate) mod protocol;
 
 pub use self::interface::*;
-use crate::stats::{record_counter, register_counter};
+use crate::samplers::Statistic;
+use crate::stats::{
+    delete_channel, record_counter, record_gauge, register_counter, register_gauge, Labeled,
+};
 use failure::Error;
 
 use crate::common::*;
 use crate::config::Config;
-use crate::samplers::Sampler;
+use crate::samplers::{construct, Registration, Sampler, SamplerError};
 
 use logger::*;
 use metrics::*;
@@ -19,60 +22,214 @@ use time;
 use walkdir;
 
 use std::collections::HashSet;
+use std::sync::Arc;
 
 const REFRESH: u64 = 60_000_000_000;
 
-pub struct Network<'a> {
-    config: &'a Config,
+pub const REGISTRATION: Registration = Registration {
+    name: "network",
+    section: "network",
+    config: |config| config.network(),
+    changed: |old, new| old.network() != new.network(),
+    enabled: |config| config.network().enabled(),
+    new: construct::<Network>,
+};
+
+/// The speed, in megabits per second, assumed for interfaces which do not
+/// report their speed, such as virtual interfaces
+const DEFAULT_SPEED: u64 = 100_000;
+
+pub struct Network {
+    config: Arc<Config>,
+    filter: Filter,
     initialized: bool,
     interfaces: HashSet<Interface>,
+    /// the interfaces whose traffic is also counted by a monitored master,
+    /// such as the members of a bond, which are left out of the totals
+    enslaved: HashSet<Interface>,
     last_refreshed: u64,
-    recorder: &'a Recorder<AtomicU32>,
+    /// the bandwidth, in bytes per second, for which the channels of the
+    /// totals are registered
+    total_bandwidth_bytes: u64,
+    recorder: Recorder<AtomicU32>,
+    /// the interfaces for which per-interface channels are registered
+    registered: HashSet<Interface>,
+}
+
+/// Labels the statistic with the name of the `Interface`, unless it is the
+/// total across interfaces
+fn labeled<T: Statistic>(statistic: T, interface: &Interface) -> Labeled<T> {
+    match interface.name() {
+        Some(name) => statistic.per("interface", name),
+        None => Labeled::new(statistic),
+    }
 }
 
-impl<'a> Network<'a> {
+impl Network {
     fn get_interfaces(&self) -> HashSet<Interface> {
+        let sys_root = self.config.general().sys_root();
         let mut interfaces = HashSet::default();
-        for entry in walkdir::WalkDir::new("/sys/class/net/")
+        for entry in walkdir::WalkDir::new(sys_root.join("class/net"))
+            .min_depth(1)
             .max_depth(1)
             .into_iter()
             .filter_map(std::result::Result::ok)
         {
             if let Some(name) = entry.file_name().to_str().to_owned() {
                 trace!("Discovered NIC: {}", name);
-                if !name.starts_with("eth") && !name.starts_with("en") && !name.starts_with("em") {
-                    trace!("Ignore NIC: bad prefix: {}", name);
+                if !self.filter.matches(name) {
+                    trace!("Ignore NIC: filtered: {}", name);
                     continue;
                 }
-                if !net::is_nic_active(name) {
+                if !net::is_nic_active(sys_root, name) {
                     trace!("Ignore NIC: inactive: {}", name);
                     continue;
                 }
-                if let Ok(speed) = file::file_as_u64(format!("/sys/class/net/{}/speed", name)) {
-                    trace!("Monitoring NIC: {} speed: {} mbps", name, speed);
-                    let bytes_secondly = (speed * 1_000_000) / 8;
-                    interfaces.insert(Interface::new(Some(name.to_owned()), Some(bytes_secondly)));
-                } else {
-                    trace!("Ignore NIC: unknown speed: {}", name);
-                }
+                let speed =
+                    match file::file_as_u64(sys_root.join(format!("class/net/{}/speed", name))) {
+                        Ok(speed) => speed,
+                        Err(_) => {
+                            trace!(
+                                "NIC: unknown speed, assuming {} mbps: {}",
+                                DEFAULT_SPEED,
+                                name
+                            );
+                            DEFAULT_SPEED
+                        }
+                    };
+                trace!("Monitoring NIC: {} speed: {} mbps", name, speed);
+                let bytes_secondly = (speed * 1_000_000) / 8;
+                interfaces.insert(Interface::new(Some(name.to_owned()), Some(bytes_secondly)));
             }
         }
         interfaces
     }
+
+    /// identifies the monitored interfaces which are enslaved to another
+    /// monitored interface
+    fn get_enslaved(&self) -> HashSet<Interface> {
+        let sys_root = self.config.general().sys_root();
+        let names: HashSet<String> = self.interfaces.iter().filter_map(|i| i.name()).collect();
+        self.interfaces
+            .iter()
+            .filter(|interface| {
+                interface
+                    .name()
+                    .and_then(|name| net::nic_master(sys_root, &name))
+                    .map(|master| names.contains(&master))
+                    .unwrap_or(false)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// register the channels of each interface statistic for the `Interface`,
+    /// with a maximum based on its bandwidth
+    fn register_interface(&self, interface: &Interface) {
+        let bandwidth_bytes = match interface.name() {
+            Some(_) => interface.bandwidth_bytes().unwrap_or(0),
+            None => self.total_bandwidth_bytes,
+        };
+        for statistic in self.config.network().interface_statistics() {
+            let max = match statistic {
+                InterfaceStatistic::RxBytes | InterfaceStatistic::TxBytes => 2 * bandwidth_bytes,
+                _ => (2 * bandwidth_bytes / 64),
+            };
+            register_counter(
+                &self.recorder,
+                labeled(statistic, interface),
+                max,
+                3,
+                self.config.general().window(),
+                PERCENTILES,
+            );
+        }
+    }
+
+    fn deregister_interface(&self, interface: &Interface) {
+        for statistic in self.config.network().interface_statistics() {
+            delete_channel(&self.recorder, labeled(statistic, interface).to_string());
+        }
+    }
+
+    /// the combined bandwidth, in bytes per second, of the interfaces which
+    /// are counted in the totals
+    fn get_total_bandwidth_bytes(&self) -> u64 {
+        self.interfaces
+            .difference(&self.enslaved)
+            .map(|i| i.bandwidth_bytes().unwrap_or(0))
+            .sum()
+    }
+
+    /// register the channels of the totals across interfaces and of the
+    /// protocol statistics, with maximums based on the total bandwidth
+    fn register_totals(&self) {
+        self.register_interface(&Interface::new(None, None));
+        for statistic in self.config.network().protocol_statistics() {
+            if statistic.is_gauge() {
+                register_gauge(
+                    &self.recorder,
+                    statistic,
+                    MILLION,
+                    3,
+                    self.config.general().window(),
+                    PERCENTILES,
+                );
+            } else {
+                register_counter(
+                    &self.recorder,
+                    statistic,
+                    2 * self.total_bandwidth_bytes / 64,
+                    3,
+                    self.config.general().window(),
+                    PERCENTILES,
+                );
+            }
+        }
+    }
+
+    fn deregister_totals(&self) {
+        self.deregister_interface(&Interface::new(None, None));
+        for statistic in self.config.network().protocol_statistics() {
+            delete_channel(&self.recorder, statistic.to_string())
+        }
+    }
+
+    /// register channels for interfaces which have appeared and remove those
+    /// of interfaces which are gone. the totals are registered again if the
+    /// total bandwidth has changed, so their maximums stay in proportion
+    fn refresh_registered(&mut self) {
+        for interface in self.registered.difference(&self.interfaces) {
+            self.deregister_interface(interface);
+        }
+        for interface in self.interfaces.difference(&self.registered) {
+            self.register_interface(interface);
+        }
+        self.registered = self.interfaces.clone();
+        let total_bandwidth_bytes = self.get_total_bandwidth_bytes();
+        if total_bandwidth_bytes != self.total_bandwidth_bytes {
+            self.deregister_totals();
+            self.total_bandwidth_bytes = total_bandwidth_bytes;
+            self.register_totals();
+        }
+    }
 }
 
-impl<'a> Sampler<'a> for Network<'a> {
-    fn new(
-        config: &'a Config,
-        recorder: &'a Recorder<AtomicU32>,
-    ) -> Result<Option<Box<Self>>, Error> {
+impl Sampler for Network {
+    fn new(config: Arc<Config>, recorder: Recorder<AtomicU32>) -> Result<Option<Box<Self>>, Error> {
         if config.network().enabled() {
+            let filter = Filter::new(config.network().include(), config.network().exclude())
+                .map_err(|e| failure::format_err!("bad interface pattern: {}", e))?;
             Ok(Some(Box::new(Self {
                 config,
+                filter,
                 initialized: false,
                 interfaces: HashSet::new(),
+                enslaved: HashSet::new(),
                 last_refreshed: 0,
+                total_bandwidth_bytes: 0,
                 recorder,
+                registered: HashSet::new(),
             })))
         } else {
             Ok(None)
@@ -83,32 +240,42 @@ impl<'a> Sampler<'a> for Network<'a> {
         "network".to_string()
     }
 
-    fn sample(&mut self) -> Result<(), ()> {
+    fn sample(&mut self) -> Result<(), SamplerError> {
         trace!("sample {}", self.name());
         let time = time::precise_time_ns();
+        if !self.initialized {
+            self.register();
+        }
         if (time - self.last_refreshed) >= REFRESH {
             self.interfaces = self.get_interfaces();
+            self.enslaved = self.get_enslaved();
             self.last_refreshed = time;
-        }
-        if !self.initialized {
-            self.register();
+            self.refresh_registered();
         }
 
-        // interface statistics
+        // interface statistics, for each interface and in total
+        let sys_root = self.config.general().sys_root();
         for statistic in self.config.network().interface_statistics() {
-            let sum: u64 = self
-                .interfaces
-                .iter()
-                .map(|i| i.get_statistic(&statistic).unwrap_or(0))
-                .sum();
-            record_counter(self.recorder, statistic, time, sum);
+            let mut sum = 0;
+            for interface in &self.interfaces {
+                let value = interface.get_statistic(sys_root, statistic).unwrap_or(0);
+                record_counter(&self.recorder, labeled(statistic, interface), time, value);
+                if !self.enslaved.contains(interface) {
+                    sum += value;
+                }
+            }
+            record_counter(&self.recorder, statistic, time, sum);
         }
 
         // protocol statistics
-        if let Ok(protocol) = protocol::Protocol::new() {
+        if let Ok(protocol) = protocol::Protocol::new(self.config.general().proc_root()) {
             for statistic in self.config.network().protocol_statistics() {
                 let value = *protocol.get(statistic).unwrap_or(&0);
-                record_counter(self.recorder, statistic, time, value);
+                if statistic.is_gauge() {
+                    record_gauge(&self.recorder, statistic, time, value);
+                } else {
+                    record_counter(&self.recorder, statistic, time, value);
+                }
             }
         }
 
@@ -119,40 +286,11 @@ impl<'a> Sampler<'a> for Network<'a> {
         trace!("register {}", self.name());
         if !self.initialized {
             self.interfaces = self.get_interfaces();
+            self.enslaved = self.get_enslaved();
             self.last_refreshed = time::precise_time_ns();
-            let mut total_bandwidth_bytes = 0;
-            for interface in self.interfaces.iter() {
-                if interface.name().is_some() {
-                    total_bandwidth_bytes += interface.bandwidth_bytes().unwrap_or(0);
-                }
-            }
-            for statistic in self.config.network().interface_statistics() {
-                let max = match statistic {
-                    InterfaceStatistic::RxBytes | InterfaceStatistic::TxBytes => {
-                        2 * total_bandwidth_bytes
-                    }
-                    _ => (2 * total_bandwidth_bytes / 64),
-                };
-                register_counter(
-                    self.recorder,
-                    statistic,
-                    max,
-                    3,
-                    self.config.general().window(),
-                    PERCENTILES,
-                );
-            }
-            for statistic in self.config.network().protocol_statistics() {
-                let max = 2 * total_bandwidth_bytes / 64;
-                register_counter(
-                    self.recorder,
-                    statistic,
-                    max,
-                    3,
-                    self.config.general().window(),
-                    PERCENTILES,
-                );
-            }
+            self.total_bandwidth_bytes = self.get_total_bandwidth_bytes();
+            self.register_totals();
+            self.refresh_registered();
             self.initialized = true;
         }
     }
@@ -160,12 +298,11 @@ impl<'a> Sampler<'a> for Network<'a> {
     fn deregister(&mut self) {
         trace!("deregister {}", self.name());
         if self.initialized {
-            for statistic in self.config.network().interface_statistics() {
-                self.recorder.delete_channel(statistic.to_string());
-            }
-            for statistic in self.config.network().protocol_statistics() {
-                self.recorder.delete_channel(statistic.to_string())
+            self.deregister_totals();
+            for interface in &self.registered {
+                self.deregister_interface(interface);
             }
+            self.registered.clear();
             self.initialized = false;
         }
     }
@@ -5,6 +5,7 @@
 use crate::samplers::Statistic;
 use std::collections::HashMap;
 use std::fmt;
+use std::path::Path;
 
 use serde_derive::*;
 
@@ -14,10 +15,14 @@ pub struct Protocol {
 }
 
 impl Protocol {
-    pub fn new() -> Result<Self, ()> {
-        let snmp = crate::common::file::nested_map_from_file("/proc/net/snmp")?;
-        let netstat = crate::common::file::nested_map_from_file("/proc/net/netstat")?;
-        let data = snmp.into_iter().chain(netstat).collect();
+    /// Reads the IPv4 and IPv6 protocol statistics. The IPv6 statistics are
+    /// absent, rather than an error, if IPv6 is disabled
+    pub fn new(proc_root: &Path) -> Result<Self, ()> {
+        let snmp = crate::common::file::nested_map_from_file(proc_root.join("net/snmp"))?;
+        let netstat = crate::common::file::nested_map_from_file(proc_root.join("net/netstat"))?;
+        let snmp6 = crate::common::file::snmp6_map_from_file(proc_root.join("net/snmp6"))
+            .unwrap_or_default();
+        let data = snmp.into_iter().chain(netstat).chain(snmp6).collect();
         Ok(Self { data })
     }
 
@@ -33,56 +38,448 @@ impl Protocol {
 #[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Hash)]
 #[serde(deny_unknown_fields, rename_all = "snake_case")]
 pub enum ProtocolStatistic {
+    IcmpInDestUnreachs,
+    IcmpInErrors,
+    IcmpInMsgs,
+    IcmpOutDestUnreachs,
+    IcmpOutErrors,
+    IcmpOutMsgs,
+    IpFragCreates,
+    IpFragFails,
+    IpFragOks,
+    IpReasmFails,
+    IpReasmOks,
+    IpReasmReqds,
+    TcpActiveOpens,
+    TcpAttemptFails,
+    TcpCurrEstab,
+    TcpEstabResets,
+    TcpInErrs,
     TcpInSegs,
+    TcpListenDrops,
+    TcpListenOverflows,
+    TcpLossProbes,
+    TcpOutRsts,
     TcpOutSegs,
+    TcpPassiveOpens,
     TcpPruneCalled,
     TcpRcvCollapsed,
+    TcpReqQFullDoCookies,
+    TcpReqQFullDrop,
     TcpRetransSegs,
+    TcpTimeouts,
     UdpInDatagrams,
     UdpInErrors,
+    UdpNoPorts,
     UdpOutDatagrams,
+    UdpRcvbufErrors,
+    UdpSndbufErrors,
+    Icmp6InDestUnreachs,
+    Icmp6InErrors,
+    Icmp6InMsgs,
+    Icmp6OutDestUnreachs,
+    Icmp6OutErrors,
+    Icmp6OutMsgs,
+    Ip6FragCreates,
+    Ip6FragFails,
+    Ip6FragOks,
+    Ip6InDelivers,
+    Ip6InDiscards,
+    Ip6InNoRoutes,
+    Ip6InReceives,
+    Ip6OutDiscards,
+    Ip6OutNoRoutes,
+    Ip6OutRequests,
+    Ip6ReasmFails,
+    Ip6ReasmOks,
+    Ip6ReasmReqds,
+    Udp6InDatagrams,
+    Udp6InErrors,
+    Udp6NoPorts,
+    Udp6OutDatagrams,
+    Udp6RcvbufErrors,
+    Udp6SndbufErrors,
 }
 
 impl fmt::Display for ProtocolStatistic {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
+            ProtocolStatistic::IcmpInDestUnreachs => write!(f, "network/icmp/receive/unreachable"),
+            ProtocolStatistic::IcmpInErrors => write!(f, "network/icmp/receive/errors"),
+            ProtocolStatistic::IcmpInMsgs => write!(f, "network/icmp/receive/messages"),
+            ProtocolStatistic::IcmpOutDestUnreachs => {
+                write!(f, "network/icmp/transmit/unreachable")
+            }
+            ProtocolStatistic::IcmpOutErrors => write!(f, "network/icmp/transmit/errors"),
+            ProtocolStatistic::IcmpOutMsgs => write!(f, "network/icmp/transmit/messages"),
+            ProtocolStatistic::IpFragCreates => write!(f, "network/ip/fragmentation/created"),
+            ProtocolStatistic::IpFragFails => write!(f, "network/ip/fragmentation/failed"),
+            ProtocolStatistic::IpFragOks => write!(f, "network/ip/fragmentation/ok"),
+            ProtocolStatistic::IpReasmFails => write!(f, "network/ip/reassembly/failed"),
+            ProtocolStatistic::IpReasmOks => write!(f, "network/ip/reassembly/ok"),
+            ProtocolStatistic::IpReasmReqds => write!(f, "network/ip/reassembly/required"),
+            ProtocolStatistic::TcpActiveOpens => write!(f, "network/tcp/connections/active_opens"),
+            ProtocolStatistic::TcpAttemptFails => {
+                write!(f, "network/tcp/connections/attempt_fails")
+            }
+            ProtocolStatistic::TcpCurrEstab => write!(f, "network/tcp/connections/established"),
+            ProtocolStatistic::TcpEstabResets => write!(f, "network/tcp/connections/resets"),
+            ProtocolStatistic::TcpInErrs => write!(f, "network/tcp/receive/errors"),
             ProtocolStatistic::TcpInSegs => write!(f, "network/tcp/receive/segments"),
+            ProtocolStatistic::TcpListenDrops => write!(f, "network/tcp/receive/listen_drops"),
+            ProtocolStatistic::TcpListenOverflows => {
+                write!(f, "network/tcp/receive/listen_overflows")
+            }
+            ProtocolStatistic::TcpLossProbes => write!(f, "network/tcp/transmit/loss_probes"),
+            ProtocolStatistic::TcpOutRsts => write!(f, "network/tcp/transmit/resets"),
             ProtocolStatistic::TcpOutSegs => write!(f, "network/tcp/transmit/segments"),
+            ProtocolStatistic::TcpPassiveOpens => {
+                write!(f, "network/tcp/connections/passive_opens")
+            }
             ProtocolStatistic::TcpPruneCalled => write!(f, "network/tcp/receive/prune_called"),
             ProtocolStatistic::TcpRcvCollapsed => write!(f, "network/tcp/receive/collapsed"),
+            ProtocolStatistic::TcpReqQFullDoCookies => write!(f, "network/tcp/receive/syn_cookies"),
+            ProtocolStatistic::TcpReqQFullDrop => write!(f, "network/tcp/receive/syn_drops"),
             ProtocolStatistic::TcpRetransSegs => write!(f, "network/tcp/transmit/retranmits"),
+            ProtocolStatistic::TcpTimeouts => write!(f, "network/tcp/timeouts"),
             ProtocolStatistic::UdpInDatagrams => write!(f, "network/udp/receive/datagrams"),
             ProtocolStatistic::UdpInErrors => write!(f, "network/udp/receive/errors"),
+            ProtocolStatistic::UdpNoPorts => write!(f, "network/udp/receive/no_ports"),
             ProtocolStatistic::UdpOutDatagrams => write!(f, "network/udp/transmit/datagrams"),
+            ProtocolStatistic::UdpRcvbufErrors => write!(f, "network/udp/receive/buffer_errors"),
+            ProtocolStatistic::UdpSndbufErrors => write!(f, "network/udp/transmit/buffer_errors"),
+            ProtocolStatistic::Icmp6InDestUnreachs => {
+                write!(f, "network/icmp6/receive/unreachable")
+            }
+            ProtocolStatistic::Icmp6InErrors => write!(f, "network/icmp6/receive/errors"),
+            ProtocolStatistic::Icmp6InMsgs => write!(f, "network/icmp6/receive/messages"),
+            ProtocolStatistic::Icmp6OutDestUnreachs => {
+                write!(f, "network/icmp6/transmit/unreachable")
+            }
+            ProtocolStatistic::Icmp6OutErrors => write!(f, "network/icmp6/transmit/errors"),
+            ProtocolStatistic::Icmp6OutMsgs => write!(f, "network/icmp6/transmit/messages"),
+            ProtocolStatistic::Ip6FragCreates => write!(f, "network/ip6/fragmentation/created"),
+            ProtocolStatistic::Ip6FragFails => write!(f, "network/ip6/fragmentation/failed"),
+            ProtocolStatistic::Ip6FragOks => write!(f, "network/ip6/fragmentation/ok"),
+            ProtocolStatistic::Ip6InDelivers => write!(f, "network/ip6/receive/delivered"),
+            ProtocolStatistic::Ip6InDiscards => write!(f, "network/ip6/receive/discards"),
+            ProtocolStatistic::Ip6InNoRoutes => write!(f, "network/ip6/receive/no_routes"),
+            ProtocolStatistic::Ip6InReceives => write!(f, "network/ip6/receive/packets"),
+            ProtocolStatistic::Ip6OutDiscards => write!(f, "network/ip6/transmit/discards"),
+            ProtocolStatistic::Ip6OutNoRoutes => write!(f, "network/ip6/transmit/no_routes"),
+            ProtocolStatistic::Ip6OutRequests => write!(f, "network/ip6/transmit/packets"),
+            ProtocolStatistic::Ip6ReasmFails => write!(f, "network/ip6/reassembly/failed"),
+            ProtocolStatistic::Ip6ReasmOks => write!(f, "network/ip6/reassembly/ok"),
+            ProtocolStatistic::Ip6ReasmReqds => write!(f, "network/ip6/reassembly/required"),
+            ProtocolStatistic::Udp6InDatagrams => write!(f, "network/udp6/receive/datagrams"),
+            ProtocolStatistic::Udp6InErrors => write!(f, "network/udp6/receive/errors"),
+            ProtocolStatistic::Udp6NoPorts => write!(f, "network/udp6/receive/no_ports"),
+            ProtocolStatistic::Udp6OutDatagrams => write!(f, "network/udp6/transmit/datagrams"),
+            ProtocolStatistic::Udp6RcvbufErrors => write!(f, "network/udp6/receive/buffer_errors"),
+            ProtocolStatistic::Udp6SndbufErrors => write!(f, "network/udp6/transmit/buffer_errors"),
         }
     }
 }
 
-impl Statistic for ProtocolStatistic {}
+impl Statistic for ProtocolStatistic {
+    fn description(&self) -> &str {
+        match self {
+            ProtocolStatistic::IcmpInDestUnreachs => {
+                "number of ICMP destination unreachable messages received"
+            }
+            ProtocolStatistic::IcmpInErrors => "number of ICMP messages received with errors",
+            ProtocolStatistic::IcmpInMsgs => "number of ICMP messages received",
+            ProtocolStatistic::IcmpOutDestUnreachs => {
+                "number of ICMP destination unreachable messages transmitted"
+            }
+            ProtocolStatistic::IcmpOutErrors => {
+                "number of ICMP messages not transmitted due to errors"
+            }
+            ProtocolStatistic::IcmpOutMsgs => "number of ICMP messages transmitted",
+            ProtocolStatistic::IpFragCreates => "number of IP fragments created by fragmentation",
+            ProtocolStatistic::IpFragFails => {
+                "number of IP datagrams discarded because they could not be fragmented"
+            }
+            ProtocolStatistic::IpFragOks => "number of IP datagrams which were fragmented",
+            ProtocolStatistic::IpReasmFails => "number of failures to reassemble IP fragments",
+            ProtocolStatistic::IpReasmOks => "number of IP datagrams reassembled from fragments",
+            ProtocolStatistic::IpReasmReqds => {
+                "number of IP fragments received which needed reassembly"
+            }
+            ProtocolStatistic::TcpActiveOpens => "number of TCP connections opened by connecting",
+            ProtocolStatistic::TcpAttemptFails => "number of failed TCP connection attempts",
+            ProtocolStatistic::TcpCurrEstab => {
+                "number of TCP connections which are established or closing"
+            }
+            ProtocolStatistic::TcpEstabResets => {
+                "number of established TCP connections which were reset"
+            }
+            ProtocolStatistic::TcpInErrs => "number of TCP segments received with errors",
+            ProtocolStatistic::TcpInSegs => "number of TCP segments received",
+            ProtocolStatistic::TcpListenDrops => {
+                "number of SYNs to listening sockets which were dropped"
+            }
+            ProtocolStatistic::TcpListenOverflows => {
+                "number of times the accept queue of a listening socket overflowed"
+            }
+            ProtocolStatistic::TcpLossProbes => "number of TCP tail loss probes transmitted",
+            ProtocolStatistic::TcpOutRsts => "number of TCP segments transmitted with the RST flag",
+            ProtocolStatistic::TcpOutSegs => "number of TCP segments transmitted",
+            ProtocolStatistic::TcpPassiveOpens => "number of TCP connections opened by accepting",
+            ProtocolStatistic::TcpPruneCalled => {
+                "number of times the TCP receive queue was pruned due to memory pressure"
+            }
+            ProtocolStatistic::TcpRcvCollapsed => {
+                "number of TCP segments collapsed in the receive queue due to memory pressure"
+            }
+            ProtocolStatistic::TcpReqQFullDoCookies => {
+                "number of SYN cookies transmitted because the SYN queue was full"
+            }
+            ProtocolStatistic::TcpReqQFullDrop => {
+                "number of SYNs dropped because the SYN queue was full"
+            }
+            ProtocolStatistic::TcpRetransSegs => "number of TCP segments retransmitted",
+            ProtocolStatistic::TcpTimeouts => "number of TCP retransmission timeouts",
+            ProtocolStatistic::UdpInDatagrams => "number of UDP datagrams received",
+            ProtocolStatistic::UdpInErrors => "number of errors on incoming UDP datagrams",
+            ProtocolStatistic::UdpNoPorts => {
+                "number of UDP datagrams received for a port without a listener"
+            }
+            ProtocolStatistic::UdpOutDatagrams => "number of UDP datagrams transmitted",
+            ProtocolStatistic::UdpRcvbufErrors => {
+                "number of UDP datagrams dropped because the receive buffer was full"
+            }
+            ProtocolStatistic::UdpSndbufErrors => {
+                "number of UDP datagrams dropped because the send buffer was full"
+            }
+            ProtocolStatistic::Icmp6InDestUnreachs => {
+                "number of ICMPv6 destination unreachable messages received"
+            }
+            ProtocolStatistic::Icmp6InErrors => "number of ICMPv6 messages received with errors",
+            ProtocolStatistic::Icmp6InMsgs => "number of ICMPv6 messages received",
+            ProtocolStatistic::Icmp6OutDestUnreachs => {
+                "number of ICMPv6 destination unreachable messages transmitted"
+            }
+            ProtocolStatistic::Icmp6OutErrors => {
+                "number of ICMPv6 messages not transmitted due to errors"
+            }
+            ProtocolStatistic::Icmp6OutMsgs => "number of ICMPv6 messages transmitted",
+            ProtocolStatistic::Ip6FragCreates => {
+                "number of IPv6 fragments created by fragmentation"
+            }
+            ProtocolStatistic::Ip6FragFails => {
+                "number of IPv6 packets discarded because they could not be fragmented"
+            }
+            ProtocolStatistic::Ip6FragOks => "number of IPv6 packets which were fragmented",
+            ProtocolStatistic::Ip6InDelivers => "number of IPv6 packets delivered to upper layers",
+            ProtocolStatistic::Ip6InDiscards => {
+                "number of IPv6 packets discarded on receive without an error"
+            }
+            ProtocolStatistic::Ip6InNoRoutes => {
+                "number of IPv6 packets discarded on receive without a route"
+            }
+            ProtocolStatistic::Ip6InReceives => "number of IPv6 packets received",
+            ProtocolStatistic::Ip6OutDiscards => {
+                "number of IPv6 packets discarded on transmit without an error"
+            }
+            ProtocolStatistic::Ip6OutNoRoutes => {
+                "number of IPv6 packets discarded on transmit without a route"
+            }
+            ProtocolStatistic::Ip6OutRequests => {
+                "number of IPv6 packets supplied for transmission by upper layers"
+            }
+            ProtocolStatistic::Ip6ReasmFails => "number of failures to reassemble IPv6 fragments",
+            ProtocolStatistic::Ip6ReasmOks => "number of IPv6 packets reassembled from fragments",
+            ProtocolStatistic::Ip6ReasmReqds => {
+                "number of IPv6 fragments received which needed reassembly"
+            }
+            ProtocolStatistic::Udp6InDatagrams => "number of UDP datagrams received over IPv6",
+            ProtocolStatistic::Udp6InErrors => {
+                "number of errors on incoming UDP datagrams over IPv6"
+            }
+            ProtocolStatistic::Udp6NoPorts => {
+                "number of UDP datagrams received over IPv6 for a port without a listener"
+            }
+            ProtocolStatistic::Udp6OutDatagrams => "number of UDP datagrams transmitted over IPv6",
+            ProtocolStatistic::Udp6RcvbufErrors => {
+                "number of UDP datagrams over IPv6 dropped because the receive buffer was full"
+            }
+            ProtocolStatistic::Udp6SndbufErrors => {
+                "number of UDP datagrams over IPv6 dropped because the send buffer was full"
+            }
+        }
+    }
+}
 
 impl ProtocolStatistic {
+    /// The name of the field in `/proc/net/snmp`, `/proc/net/netstat` or
+    /// `/proc/net/snmp6`
     pub fn name(&self) -> &str {
         match self {
+            ProtocolStatistic::IcmpInDestUnreachs => "InDestUnreachs",
+            ProtocolStatistic::IcmpInErrors => "InErrors",
+            ProtocolStatistic::IcmpInMsgs => "InMsgs",
+            ProtocolStatistic::IcmpOutDestUnreachs => "OutDestUnreachs",
+            ProtocolStatistic::IcmpOutErrors => "OutErrors",
+            ProtocolStatistic::IcmpOutMsgs => "OutMsgs",
+            ProtocolStatistic::IpFragCreates => "FragCreates",
+            ProtocolStatistic::IpFragFails => "FragFails",
+            ProtocolStatistic::IpFragOks => "FragOKs",
+            ProtocolStatistic::IpReasmFails => "ReasmFails",
+            ProtocolStatistic::IpReasmOks => "ReasmOKs",
+            ProtocolStatistic::IpReasmReqds => "ReasmReqds",
+            ProtocolStatistic::TcpActiveOpens => "ActiveOpens",
+            ProtocolStatistic::TcpAttemptFails => "AttemptFails",
+            ProtocolStatistic::TcpCurrEstab => "CurrEstab",
+            ProtocolStatistic::TcpEstabResets => "EstabResets",
+            ProtocolStatistic::TcpInErrs => "InErrs",
             ProtocolStatistic::TcpInSegs => "InSegs",
+            ProtocolStatistic::TcpListenDrops => "ListenDrops",
+            ProtocolStatistic::TcpListenOverflows => "ListenOverflows",
+            ProtocolStatistic::TcpLossProbes => "TCPLossProbes",
+            ProtocolStatistic::TcpOutRsts => "OutRsts",
             ProtocolStatistic::TcpOutSegs => "OutSegs",
+            ProtocolStatistic::TcpPassiveOpens => "PassiveOpens",
             ProtocolStatistic::TcpPruneCalled => "PruneCalled",
             ProtocolStatistic::TcpRcvCollapsed => "TCPRcvCollapsed",
+            ProtocolStatistic::TcpReqQFullDoCookies => "TCPReqQFullDoCookies",
+            ProtocolStatistic::TcpReqQFullDrop => "TCPReqQFullDrop",
             ProtocolStatistic::TcpRetransSegs => "RetransSegs",
+            ProtocolStatistic::TcpTimeouts => "TCPTimeouts",
             ProtocolStatistic::UdpInDatagrams => "InDatagrams",
             ProtocolStatistic::UdpInErrors => "InErrors",
+            ProtocolStatistic::UdpNoPorts => "NoPorts",
             ProtocolStatistic::UdpOutDatagrams => "OutDatagrams",
+            ProtocolStatistic::UdpRcvbufErrors => "RcvbufErrors",
+            ProtocolStatistic::UdpSndbufErrors => "SndbufErrors",
+            ProtocolStatistic::Icmp6InDestUnreachs => "InDestUnreachs",
+            ProtocolStatistic::Icmp6InErrors => "InErrors",
+            ProtocolStatistic::Icmp6InMsgs => "InMsgs",
+            ProtocolStatistic::Icmp6OutDestUnreachs => "OutDestUnreachs",
+            ProtocolStatistic::Icmp6OutErrors => "OutErrors",
+            ProtocolStatistic::Icmp6OutMsgs => "OutMsgs",
+            ProtocolStatistic::Ip6FragCreates => "FragCreates",
+            ProtocolStatistic::Ip6FragFails => "FragFails",
+            ProtocolStatistic::Ip6FragOks => "FragOKs",
+            ProtocolStatistic::Ip6InDelivers => "InDelivers",
+            ProtocolStatistic::Ip6InDiscards => "InDiscards",
+            ProtocolStatistic::Ip6InNoRoutes => "InNoRoutes",
+            ProtocolStatistic::Ip6InReceives => "InReceives",
+            ProtocolStatistic::Ip6OutDiscards => "OutDiscards",
+            ProtocolStatistic::Ip6OutNoRoutes => "OutNoRoutes",
+            ProtocolStatistic::Ip6OutRequests => "OutRequests",
+            ProtocolStatistic::Ip6ReasmFails => "ReasmFails",
+            ProtocolStatistic::Ip6ReasmOks => "ReasmOKs",
+            ProtocolStatistic::Ip6ReasmReqds => "ReasmReqds",
+            ProtocolStatistic::Udp6InDatagrams => "InDatagrams",
+            ProtocolStatistic::Udp6InErrors => "InErrors",
+            ProtocolStatistic::Udp6NoPorts => "NoPorts",
+            ProtocolStatistic::Udp6OutDatagrams => "OutDatagrams",
+            ProtocolStatistic::Udp6RcvbufErrors => "RcvbufErrors",
+            ProtocolStatistic::Udp6SndbufErrors => "SndbufErrors",
         }
     }
 
+    /// The section of `/proc/net/snmp`, `/proc/net/netstat` or
+    /// `/proc/net/snmp6` with the field
     pub fn protocol(&self) -> &str {
         match self {
-            ProtocolStatistic::TcpInSegs
+            ProtocolStatistic::IcmpInDestUnreachs
+            | ProtocolStatistic::IcmpInErrors
+            | ProtocolStatistic::IcmpInMsgs
+            | ProtocolStatistic::IcmpOutDestUnreachs
+            | ProtocolStatistic::IcmpOutErrors
+            | ProtocolStatistic::IcmpOutMsgs => "Icmp:",
+            ProtocolStatistic::IpFragCreates
+            | ProtocolStatistic::IpFragFails
+            | ProtocolStatistic::IpFragOks
+            | ProtocolStatistic::IpReasmFails
+            | ProtocolStatistic::IpReasmOks
+            | ProtocolStatistic::IpReasmReqds => "Ip:",
+            ProtocolStatistic::TcpActiveOpens
+            | ProtocolStatistic::TcpAttemptFails
+            | ProtocolStatistic::TcpCurrEstab
+            | ProtocolStatistic::TcpEstabResets
+            | ProtocolStatistic::TcpInErrs
+            | ProtocolStatistic::TcpInSegs
+            | ProtocolStatistic::TcpOutRsts
             | ProtocolStatistic::TcpOutSegs
+            | ProtocolStatistic::TcpPassiveOpens
             | ProtocolStatistic::TcpRetransSegs => "Tcp:",
-            ProtocolStatistic::TcpPruneCalled | ProtocolStatistic::TcpRcvCollapsed => "TcpExt:",
+            ProtocolStatistic::TcpListenDrops
+            | ProtocolStatistic::TcpListenOverflows
+            | ProtocolStatistic::TcpLossProbes
+            | ProtocolStatistic::TcpPruneCalled
+            | ProtocolStatistic::TcpRcvCollapsed
+            | ProtocolStatistic::TcpReqQFullDoCookies
+            | ProtocolStatistic::TcpReqQFullDrop
+            | ProtocolStatistic::TcpTimeouts => "TcpExt:",
             ProtocolStatistic::UdpInDatagrams
             | ProtocolStatistic::UdpInErrors
-            | ProtocolStatistic::UdpOutDatagrams => "Udp:",
+            | ProtocolStatistic::UdpNoPorts
+            | ProtocolStatistic::UdpOutDatagrams
+            | ProtocolStatistic::UdpRcvbufErrors
+            | ProtocolStatistic::UdpSndbufErrors => "Udp:",
+            ProtocolStatistic::Icmp6InDestUnreachs
+            | ProtocolStatistic::Icmp6InErrors
+            | ProtocolStatistic::Icmp6InMsgs
+            | ProtocolStatistic::Icmp6OutDestUnreachs
+            | ProtocolStatistic::Icmp6OutErrors
+            | ProtocolStatistic::Icmp6OutMsgs => "Icmp6:",
+            ProtocolStatistic::Ip6FragCreates
+            | ProtocolStatistic::Ip6FragFails
+            | ProtocolStatistic::Ip6FragOks
+            | ProtocolStatistic::Ip6InDelivers
+            | ProtocolStatistic::Ip6InDiscards
+            | ProtocolStatistic::Ip6InNoRoutes
+            | ProtocolStatistic::Ip6InReceives
+            | ProtocolStatistic::Ip6OutDiscards
+            | ProtocolStatistic::Ip6OutNoRoutes
+            | ProtocolStatistic::Ip6OutRequests
+            | ProtocolStatistic::Ip6ReasmFails
+            | ProtocolStatistic::Ip6ReasmOks
+            | ProtocolStatistic::Ip6ReasmReqds => "Ip6:",
+            ProtocolStatistic::Udp6InDatagrams
+            | ProtocolStatistic::Udp6InErrors
+            | ProtocolStatistic::Udp6NoPorts
+            | ProtocolStatistic::Udp6OutDatagrams
+            | ProtocolStatistic::Udp6RcvbufErrors
+            | ProtocolStatistic::Udp6SndbufErrors => "Udp6:",
         }
     }
+
+    /// Returns true for statistics which are a current value rather than a
+    /// count of events
+    pub fn is_gauge(&self) -> bool {
+        *self == ProtocolStatistic::TcpCurrEstab
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_protocol() {
+        let protocol = Protocol::new(Path::new("tests/data/proc")).unwrap();
+        let get = |statistic| protocol.get(&statistic).cloned();
+        assert_eq!(get(ProtocolStatistic::IpReasmReqds), Some(40));
+        assert_eq!(get(ProtocolStatistic::IpFragCreates), Some(21));
+        assert_eq!(get(ProtocolStatistic::IcmpInMsgs), Some(31));
+        assert_eq!(get(ProtocolStatistic::IcmpOutDestUnreachs), Some(15));
+        assert_eq!(get(ProtocolStatistic::TcpActiveOpens), Some(104));
+        assert_eq!(get(ProtocolStatistic::TcpPassiveOpens), Some(87));
+        assert_eq!(get(ProtocolStatistic::TcpCurrEstab), Some(22));
+        assert_eq!(get(ProtocolStatistic::TcpOutRsts), Some(11));
+        assert_eq!(get(ProtocolStatistic::UdpInDatagrams), Some(1200));
+        assert_eq!(get(ProtocolStatistic::UdpRcvbufErrors), Some(3));
+        assert_eq!(get(ProtocolStatistic::TcpListenOverflows), Some(13));
+        assert_eq!(get(ProtocolStatistic::TcpListenDrops), Some(16));
+        assert_eq!(get(ProtocolStatistic::TcpTimeouts), Some(27));
+        assert_eq!(get(ProtocolStatistic::TcpReqQFullDrop), Some(3));
+        assert_eq!(get(ProtocolStatistic::Ip6InReceives), Some(5017));
+        assert_eq!(get(ProtocolStatistic::Ip6ReasmOks), Some(9));
+        assert_eq!(get(ProtocolStatistic::Icmp6InDestUnreachs), Some(7));
+        assert_eq!(get(ProtocolStatistic::Udp6InErrors), Some(2));
+    }
 }
@@ -89,7 +89,36 @@ impl PerfStatistic {
     }
 }
 
-impl Statistic for PerfStatistic {}
+impl Statistic for PerfStatistic {
+    fn description(&self) -> &str {
+        match self {
+            PerfStatistic::CacheMisses => "number of cache references resulting in miss",
+            PerfStatistic::CacheReferences => "total number of cache references",
+            PerfStatistic::ContextSwitches => "number of context switches",
+            PerfStatistic::CpuBranchInstructions => "total number of branch instructions",
+            PerfStatistic::CpuBranchMisses => "number of branch predictions missed",
+            PerfStatistic::CpuCycles => {
+                "number of cycles, may not be accurate with frequency scaling"
+            }
+            PerfStatistic::CpuInstructions => "number of instructions retired",
+            PerfStatistic::CpuMigrations => "number of times a task migrated between cores",
+            PerfStatistic::CpuRefCycles => {
+                "number of reference cycles, not affected by frequency scaling"
+            }
+            PerfStatistic::DtlbLoads => "total number of read references to the dTLB",
+            PerfStatistic::DtlbLoadMisses => "number of dTLB reads resulting in miss",
+            PerfStatistic::DtlbStores => "total number of write references to the dTLB",
+            PerfStatistic::DtlbStoreMisses => "number of dTLB writes resulting in miss",
+            PerfStatistic::MemoryLoads => "total number of read references to local memory",
+            PerfStatistic::MemoryLoadMisses => "number of local memory reads resulting in miss",
+            PerfStatistic::MemoryStores => "total number of write references to local memory",
+            PerfStatistic::MemoryStoreMisses => "number of local memory writes resulting in miss",
+            PerfStatistic::PageFaults => "number of page faults",
+            PerfStatistic::StalledCyclesBackend => "number of cycles stalled in the backend",
+            PerfStatistic::StalledCyclesFrontend => "number of cycles stalled in the frontend",
+        }
+    }
+}
 
 impl fmt::Display for PerfStatistic {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
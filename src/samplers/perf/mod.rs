@@ -5,12 +5,12 @@
 mod event;
 
 pub use self::event::PerfStatistic;
-use crate::stats::{record_counter, register_counter};
+use crate::stats::{delete_channel, record_counter, register_counter};
 use failure::Error;
 
 use crate::common::*;
 use crate::config::Config;
-use crate::samplers::Sampler;
+use crate::samplers::{construct, Registration, Sampler, SamplerError};
 
 use logger::*;
 use metrics::*;
@@ -19,22 +19,29 @@ use perfcnt::PerfCounter;
 use time;
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
-pub struct Perf<'a> {
-    config: &'a Config,
+pub const REGISTRATION: Registration = Registration {
+    name: "perf",
+    section: "perf",
+    config: |config| config.perf(),
+    changed: |old, new| old.perf() != new.perf(),
+    enabled: |config| config.perf().enabled(),
+    new: construct::<Perf>,
+};
+
+pub struct Perf {
+    config: Arc<Config>,
     counters: HashMap<PerfStatistic, Vec<PerfCounter>>,
     initialized: bool,
-    recorder: &'a Recorder<AtomicU32>,
+    recorder: Recorder<AtomicU32>,
 }
 
-impl<'a> Sampler<'a> for Perf<'a> {
-    fn new(
-        config: &'a Config,
-        recorder: &'a Recorder<AtomicU32>,
-    ) -> Result<Option<Box<Self>>, Error> {
+impl Sampler for Perf {
+    fn new(config: Arc<Config>, recorder: Recorder<AtomicU32>) -> Result<Option<Box<Self>>, Error> {
         if config.perf().enabled() {
             let mut counters = HashMap::new();
-            let cores = hardware_threads().unwrap_or(1);
+            let cores = hardware_threads(config.general().sys_root()).unwrap_or(1);
 
             for statistic in config.perf().statistics() {
                 let mut event_counters = Vec::new();
@@ -73,7 +80,7 @@ impl<'a> Sampler<'a> for Perf<'a> {
         "perf".to_string()
     }
 
-    fn sample(&mut self) -> Result<(), ()> {
+    fn sample(&mut self) -> Result<(), SamplerError> {
         trace!("sample {}", self.name());
         let time = time::precise_time_ns();
         let mut current = HashMap::new();
@@ -98,7 +105,7 @@ impl<'a> Sampler<'a> for Perf<'a> {
         for statistic in self.counters.keys() {
             if let Some(counter) = current.get(statistic) {
                 let value: u64 = counter.iter().sum();
-                record_counter(self.recorder, statistic, time, value);
+                record_counter(&self.recorder, statistic, time, value);
             }
         }
         Ok(())
@@ -109,7 +116,7 @@ impl<'a> Sampler<'a> for Perf<'a> {
         if !self.initialized {
             for statistic in self.counters.keys() {
                 register_counter(
-                    self.recorder,
+                    &self.recorder,
                     statistic,
                     TRILLION,
                     3,
@@ -125,7 +132,7 @@ impl<'a> Sampler<'a> for Perf<'a> {
         trace!("deregister {}", self.name());
         if self.initialized {
             for statistic in self.counters.keys() {
-                self.recorder.delete_channel(statistic.to_string());
+                delete_channel(&self.recorder, statistic.to_string());
             }
             self.initialized = false;
         }
@@ -4,21 +4,38 @@
 
 use crate::common::*;
 use crate::config::Config;
-use crate::samplers::Sampler;
-use crate::stats::{record_counter, record_gauge, register_counter, register_gauge};
+use crate::samplers::{construct, Registration, Sampler, SamplerError};
+use crate::stats::{
+    delete_channel, record_counter, record_gauge, register_counter, register_gauge, Unit,
+};
 use failure::Error;
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::Arc;
 
 use logger::*;
 use metrics::*;
 use serde_derive::*;
 use time;
 
-pub struct Rezolus<'a> {
-    config: &'a Config,
+/// The stats of Rezolus itself are always read from the procfs of its own
+/// namespace, rather than from `proc_root`, which may be a host or fixture
+/// procfs where `self` is some other process
+const PROC_SELF: &str = "/proc/self";
+
+pub const REGISTRATION: Registration = Registration {
+    name: "rezolus",
+    section: "general",
+    config: |config| config.general(),
+    changed: |old, new| old.general() != new.general(),
+    enabled: |_config| true,
+    new: construct::<Rezolus>,
+};
+
+pub struct Rezolus {
+    config: Arc<Config>,
     initialized: bool,
-    recorder: &'a Recorder<AtomicU32>,
+    recorder: Recorder<AtomicU32>,
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Hash)]
@@ -41,6 +58,24 @@ impl std::fmt::Display for Statistic {
     }
 }
 
+impl crate::samplers::Statistic for Statistic {
+    fn description(&self) -> &str {
+        match self {
+            Statistic::MemoryVirtual => "virtual address space of rezolus in bytes",
+            Statistic::MemoryResident => "bytes of RAM occupied by rezolus",
+            Statistic::CpuUser => "time, in nanoseconds, rezolus spent in user-space",
+            Statistic::CpuKernel => "time, in nanoseconds, rezolus spent in kernel-mode",
+        }
+    }
+
+    fn unit(&self) -> Option<Unit> {
+        match self {
+            Statistic::MemoryVirtual | Statistic::MemoryResident => Some(Unit::Bytes),
+            Statistic::CpuUser | Statistic::CpuKernel => Some(Unit::Nanoseconds),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 /// provides information about memory usage in pages
 pub enum ProcessMemory {
@@ -56,26 +91,25 @@ pub enum ProcessMemory {
     Data,
 }
 
-fn parse_process_memory_stats<P: AsRef<Path>>(path: P) -> HashMap<ProcessMemory, u64> {
+fn parse_process_memory_stats<P: AsRef<Path>>(
+    path: P,
+) -> Result<HashMap<ProcessMemory, u64>, SamplerError> {
     let mut result = HashMap::new();
-    let content = file::string_from_file(path).expect("failed to read statm");
+    let content = file::string_from_file(path)
+        .map_err(|_| SamplerError::Transient("failed to read statm".to_string()))?;
     let tokens: Vec<&str> = content.split_whitespace().collect();
-    if let Ok(size) = tokens[0].parse::<u64>() {
-        result.insert(ProcessMemory::Size, size);
-    }
-    if let Ok(resident) = tokens[1].parse::<u64>() {
-        result.insert(ProcessMemory::Resident, resident);
-    }
-    if let Ok(shared) = tokens[2].parse::<u64>() {
-        result.insert(ProcessMemory::Shared, shared);
-    }
-    if let Ok(text) = tokens[3].parse::<u64>() {
-        result.insert(ProcessMemory::Text, text);
-    }
-    if let Ok(data) = tokens[5].parse::<u64>() {
-        result.insert(ProcessMemory::Data, data);
+    for (index, field) in &[
+        (0, ProcessMemory::Size),
+        (1, ProcessMemory::Resident),
+        (2, ProcessMemory::Shared),
+        (3, ProcessMemory::Text),
+        (5, ProcessMemory::Data),
+    ] {
+        if let Some(Ok(value)) = tokens.get(*index).map(|v| v.parse::<u64>()) {
+            result.insert(field.clone(), value);
+        }
     }
-    result
+    Ok(result)
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -91,62 +125,48 @@ pub enum ProcessStat {
     ChildrenSystemTime,
 }
 
-fn parse_process_stats<P: AsRef<Path>>(path: P) -> HashMap<ProcessStat, u64> {
+fn parse_process_stats<P: AsRef<Path>>(path: P) -> Result<HashMap<ProcessStat, u64>, SamplerError> {
     let mut result = HashMap::new();
-    let content = file::string_from_file(path).expect("failed to read stat");
+    let content = file::string_from_file(path)
+        .map_err(|_| SamplerError::Transient("failed to read stat".to_string()))?;
     let tokens: Vec<&str> = content.split_whitespace().collect();
-    if let Ok(utime) = tokens[13].parse::<u64>() {
-        result.insert(ProcessStat::UserTime, utime);
-    }
-    if let Ok(stime) = tokens[14].parse::<u64>() {
-        result.insert(ProcessStat::SystemTime, stime);
-    }
-    if let Ok(cutime) = tokens[15].parse::<u64>() {
-        result.insert(ProcessStat::ChildrenUserTime, cutime);
-    }
-    if let Ok(cstime) = tokens[16].parse::<u64>() {
-        result.insert(ProcessStat::ChildrenSystemTime, cstime);
+    for (index, field) in &[
+        (13, ProcessStat::UserTime),
+        (14, ProcessStat::SystemTime),
+        (15, ProcessStat::ChildrenUserTime),
+        (16, ProcessStat::ChildrenSystemTime),
+    ] {
+        if let Some(Ok(value)) = tokens.get(*index).map(|v| v.parse::<u64>()) {
+            result.insert(field.clone(), value);
+        }
     }
-    result
+    Ok(result)
 }
 
-impl<'a> Rezolus<'a> {
-    pub fn gauges(&self) -> Vec<String> {
-        vec![
-            Statistic::MemoryResident.to_string(),
-            Statistic::MemoryVirtual.to_string(),
-        ]
+impl Rezolus {
+    fn gauges(&self) -> Vec<Statistic> {
+        vec![Statistic::MemoryResident, Statistic::MemoryVirtual]
     }
 
-    pub fn counters(&self) -> Vec<String> {
-        vec![
-            Statistic::CpuUser.to_string(),
-            Statistic::CpuKernel.to_string(),
-        ]
+    fn counters(&self) -> Vec<Statistic> {
+        vec![Statistic::CpuUser, Statistic::CpuKernel]
     }
 
-    pub fn memory_usage(&self, recorder: &Recorder<AtomicU32>) {
+    pub fn memory_usage(&self, recorder: &Recorder<AtomicU32>) -> Result<(), SamplerError> {
         let time = time::precise_time_ns();
-        let pid: u32 = std::process::id();
-        let parsed = parse_process_memory_stats(format!("/proc/{}/statm", pid));
-        record_gauge(
-            recorder,
-            Statistic::MemoryVirtual,
-            time,
-            parsed.get(&ProcessMemory::Size).unwrap() * 4096,
-        );
-        record_gauge(
-            recorder,
-            Statistic::MemoryResident,
-            time,
-            parsed.get(&ProcessMemory::Resident).unwrap() * 4096,
-        );
+        let parsed = parse_process_memory_stats(Path::new(PROC_SELF).join("statm"))?;
+        if let Some(size) = parsed.get(&ProcessMemory::Size) {
+            record_gauge(recorder, Statistic::MemoryVirtual, time, size * 4096);
+        }
+        if let Some(resident) = parsed.get(&ProcessMemory::Resident) {
+            record_gauge(recorder, Statistic::MemoryResident, time, resident * 4096);
+        }
+        Ok(())
     }
 
-    pub fn cpu_usage(&self, recorder: &Recorder<AtomicU32>) {
+    pub fn cpu_usage(&self, recorder: &Recorder<AtomicU32>) -> Result<(), SamplerError> {
         let time = time::precise_time_ns();
-        let pid: u32 = std::process::id();
-        let parsed = parse_process_stats(format!("/proc/{}/stat", pid));
+        let parsed = parse_process_stats(Path::new(PROC_SELF).join("stat"))?;
 
         let user_seconds = (*parsed.get(&ProcessStat::UserTime).unwrap_or(&0)
             + *parsed.get(&ProcessStat::ChildrenUserTime).unwrap_or(&0))
@@ -157,14 +177,12 @@ impl<'a> Rezolus<'a> {
             + *parsed.get(&ProcessStat::ChildrenSystemTime).unwrap_or(&0))
             * nanos_per_tick();
         record_counter(recorder, Statistic::CpuKernel, time, kernel_seconds);
+        Ok(())
     }
 }
 
-impl<'a> Sampler<'a> for Rezolus<'a> {
-    fn new(
-        config: &'a Config,
-        recorder: &'a Recorder<AtomicU32>,
-    ) -> Result<Option<Box<Self>>, Error> {
+impl Sampler for Rezolus {
+    fn new(config: Arc<Config>, recorder: Recorder<AtomicU32>) -> Result<Option<Box<Self>>, Error> {
         Ok(Some(Box::new(Rezolus {
             config,
             initialized: false,
@@ -176,31 +194,30 @@ impl<'a> Sampler<'a> for Rezolus<'a> {
         "rezolus".to_string()
     }
 
-    fn sample(&mut self) -> Result<(), ()> {
+    fn sample(&mut self) -> Result<(), SamplerError> {
         trace!("sample {}", self.name());
         self.register();
-        self.memory_usage(self.recorder);
-        self.cpu_usage(self.recorder);
-        Ok(())
+        self.memory_usage(&self.recorder)?;
+        self.cpu_usage(&self.recorder)
     }
 
     fn register(&mut self) {
         if !self.initialized {
             trace!("register {}", self.name());
-            for label in self.gauges() {
+            for statistic in self.gauges() {
                 register_gauge(
-                    self.recorder,
-                    label.clone(),
+                    &self.recorder,
+                    statistic,
                     32 * TERABYTE,
                     3,
                     self.config.general().window(),
                     &[Percentile::Maximum],
                 );
             }
-            for label in self.counters() {
+            for statistic in self.counters() {
                 register_counter(
-                    self.recorder,
-                    label.clone(),
+                    &self.recorder,
+                    statistic,
                     BILLION,
                     3,
                     self.config.general().window(),
@@ -214,11 +231,11 @@ impl<'a> Sampler<'a> for Rezolus<'a> {
     fn deregister(&mut self) {
         if self.initialized {
             trace!("deregister {}", self.name());
-            for label in self.gauges() {
-                self.recorder.delete_channel(label.clone());
+            for statistic in self.gauges() {
+                delete_channel(&self.recorder, statistic.to_string());
             }
-            for label in self.counters() {
-                self.recorder.delete_channel(label.clone());
+            for statistic in self.counters() {
+                delete_channel(&self.recorder, statistic.to_string());
             }
             self.initialized = false;
         }
@@ -231,7 +248,7 @@ mod tests {
 
     #[test]
     fn process_memory_stats() {
-        let parsed = parse_process_memory_stats("tests/data/proc/1000/statm");
+        let parsed = parse_process_memory_stats("tests/data/proc/1000/statm").unwrap();
         assert_eq!(parsed.get(&ProcessMemory::Size), Some(&149100));
         assert_eq!(parsed.get(&ProcessMemory::Resident), Some(&34107));
         assert_eq!(parsed.get(&ProcessMemory::Shared), Some(&19859));
@@ -241,7 +258,7 @@ mod tests {
 
     #[test]
     fn process_stats() {
-        let parsed = parse_process_stats("tests/data/proc/1000/stat");
+        let parsed = parse_process_stats("tests/data/proc/1000/stat").unwrap();
         assert_eq!(parsed.get(&ProcessStat::UserTime), Some(&1104933));
         assert_eq!(parsed.get(&ProcessStat::SystemTime), Some(&2789797));
         assert_eq!(parsed.get(&ProcessStat::ChildrenUserTime), Some(&0));
@@ -4,8 +4,8 @@
 
 use crate::common::*;
 use crate::config::Config;
-use crate::samplers::Sampler;
-use crate::stats::{record_counter, register_counter};
+use crate::samplers::{construct, Registration, Sampler, SamplerError};
+use crate::stats::{delete_channel, record_counter, register_counter};
 use failure::Error;
 
 use logger::*;
@@ -17,8 +17,9 @@ use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
+use std::sync::Arc;
 
-const SOFTNET_STAT: &str = "/proc/net/softnet_stat";
+const SOFTNET_STAT: &str = "net/softnet_stat";
 
 #[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Hash)]
 #[serde(deny_unknown_fields, rename_all = "lowercase")]
@@ -44,6 +45,25 @@ impl std::fmt::Display for Statistic {
     }
 }
 
+impl crate::samplers::Statistic for Statistic {
+    fn description(&self) -> &str {
+        match self {
+            Statistic::Processed => "number of packets processed by the kernel network stack",
+            Statistic::Dropped => "number of packets dropped by the kernel network stack",
+            Statistic::TimeSqueezed => {
+                "number of times the kernel network stack could not complete its work within its working interval"
+            }
+            Statistic::CpuCollision => {
+                "number of times a collision occurred obtaining the device lock while transmitting"
+            }
+            Statistic::ReceivedRps => {
+                "number of times the CPU was woken up by an inter-processor interrupt for RPS"
+            }
+            Statistic::FlowLimitCount => "number of times the flow limit was reached",
+        }
+    }
+}
+
 impl Statistic {
     fn field_number(&self) -> usize {
         match self {
@@ -57,49 +77,54 @@ impl Statistic {
     }
 }
 
-pub struct Softnet<'a> {
-    config: &'a Config,
+pub const REGISTRATION: Registration = Registration {
+    name: "softnet",
+    section: "softnet",
+    config: |config| config.softnet(),
+    changed: |old, new| old.softnet() != new.softnet(),
+    enabled: |config| config.softnet().enabled(),
+    new: construct::<Softnet>,
+};
+
+pub struct Softnet {
+    config: Arc<Config>,
     initialized: bool,
-    recorder: &'a Recorder<AtomicU32>,
+    recorder: Recorder<AtomicU32>,
 }
 
-pub fn read_softnet_stat<P: AsRef<Path>>(path: P) -> HashMap<Statistic, u64> {
+const STATISTICS: &[Statistic] = &[
+    Statistic::Processed,
+    Statistic::Dropped,
+    Statistic::TimeSqueezed,
+    Statistic::CpuCollision,
+    Statistic::ReceivedRps,
+    Statistic::FlowLimitCount,
+];
+
+pub fn read_softnet_stat<P: AsRef<Path>>(path: P) -> Result<HashMap<Statistic, u64>, SamplerError> {
     let mut result = HashMap::new();
     let file = File::open(path)
-        .map_err(|e| debug!("could not read softnet_stat: {}", e))
-        .expect("failed to open file");
+        .map_err(|e| SamplerError::Transient(format!("could not open softnet_stat: {}", e)))?;
 
     let file = BufReader::new(file);
     for line in file.lines() {
-        let line = line.unwrap();
+        let line = line
+            .map_err(|e| SamplerError::Transient(format!("could not read softnet_stat: {}", e)))?;
         let tokens: Vec<&str> = line.split_whitespace().collect();
         if tokens.len() != 11 {
             continue;
         }
-        for statistic in &[
-            Statistic::Processed,
-            Statistic::Dropped,
-            Statistic::TimeSqueezed,
-            Statistic::CpuCollision,
-            Statistic::ReceivedRps,
-            Statistic::FlowLimitCount,
-        ] {
-            if !result.contains_key(statistic) {
-                result.insert(*statistic, 0);
-            }
-            let current = result.get_mut(statistic).unwrap();
+        for statistic in STATISTICS {
+            let current = result.entry(*statistic).or_insert(0);
             *current += u64::from_str_radix(tokens[statistic.field_number()], 16).unwrap_or(0);
         }
     }
 
-    result
+    Ok(result)
 }
 
-impl<'a> Sampler<'a> for Softnet<'a> {
-    fn new(
-        config: &'a Config,
-        recorder: &'a Recorder<AtomicU32>,
-    ) -> Result<Option<Box<Self>>, Error> {
+impl Sampler for Softnet {
+    fn new(config: Arc<Config>, recorder: Recorder<AtomicU32>) -> Result<Option<Box<Self>>, Error> {
         if config.softnet().enabled() {
             Ok(Some(Box::new(Self {
                 config,
@@ -115,15 +140,15 @@ impl<'a> Sampler<'a> for Softnet<'a> {
         "softnet".to_string()
     }
 
-    fn sample(&mut self) -> Result<(), ()> {
+    fn sample(&mut self) -> Result<(), SamplerError> {
         trace!("sample {}", self.name());
         let time = time::precise_time_ns();
-        let data = read_softnet_stat(SOFTNET_STAT);
+        let data = read_softnet_stat(self.config.general().proc_root().join(SOFTNET_STAT))?;
         if !self.initialized {
             self.register();
         }
         for (statistic, value) in data {
-            record_counter(self.recorder, statistic, time, value);
+            record_counter(&self.recorder, statistic, time, value);
         }
         Ok(())
     }
@@ -131,11 +156,10 @@ impl<'a> Sampler<'a> for Softnet<'a> {
     fn register(&mut self) {
         trace!("register {}", self.name());
         if !self.initialized {
-            let data = read_softnet_stat(SOFTNET_STAT);
-            for statistic in data.keys() {
+            for statistic in STATISTICS {
                 register_counter(
-                    self.recorder,
-                    statistic.to_string(),
+                    &self.recorder,
+                    statistic,
                     TRILLION,
                     3,
                     self.config.general().window(),
@@ -149,9 +173,8 @@ impl<'a> Sampler<'a> for Softnet<'a> {
     fn deregister(&mut self) {
         trace!("deregister {}", self.name());
         if self.initialized {
-            let data = read_softnet_stat(SOFTNET_STAT);
-            for statistic in data.keys() {
-                self.recorder.delete_channel(statistic.to_string());
+            for statistic in STATISTICS {
+                delete_channel(&self.recorder, statistic.to_string());
             }
             self.initialized = false;
         }
@@ -164,7 +187,7 @@ mod tests {
 
     #[test]
     fn parse_softnet_stat() {
-        let data = read_softnet_stat(format!("tests/data{}", SOFTNET_STAT));
+        let data = read_softnet_stat(Path::new("tests/data/proc").join(SOFTNET_STAT)).unwrap();
         assert_eq!(data.get(&Statistic::Processed), Some(&18035263));
         assert_eq!(data.get(&Statistic::Dropped), Some(&0));
         assert_eq!(data.get(&Statistic::TimeSqueezed), Some(&135098));
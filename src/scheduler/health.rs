@@ -0,0 +1,132 @@
+// Copyright 2019 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use crate::common::{MINUTE, PERCENTILES};
+use crate::config::Config;
+use crate::samplers::SamplerError;
+use crate::stats::{
+    delete_channel, delete_distribution, record_counter, record_distribution, record_gauge,
+    register_distribution, register_value, Metric, Unit,
+};
+
+use metrics::*;
+
+use std::collections::HashMap;
+
+const ERROR_KINDS: &[&str] = &["transient", "fatal"];
+
+/// `Health` tracks how a single sampler is performing. It is owned by the
+/// sampler's worker thread and outlives restarts of the sampler, so the counts
+/// are cumulative for the life of the worker.
+pub struct Health {
+    name: String,
+    recorder: Recorder<AtomicU32>,
+    timeouts: u64,
+    errors: HashMap<&'static str, u64>,
+}
+
+impl Health {
+    pub fn new(name: &str, config: &Config, recorder: Recorder<AtomicU32>) -> Self {
+        let health = Self {
+            name: name.to_string(),
+            recorder,
+            timeouts: 0,
+            errors: HashMap::new(),
+        };
+        health.register(config);
+        health
+    }
+
+    /// Record a call to `sample()` which returned successfully
+    pub fn success(&self, start: u64, stop: u64) {
+        self.runtime(start, stop);
+        record_gauge(
+            &self.recorder,
+            self.label("last_success"),
+            stop,
+            time::get_time().sec as u64,
+        );
+    }
+
+    /// Record a call to `sample()` which returned an error, counting it by
+    /// the kind of error
+    pub fn error(&mut self, error: &SamplerError, start: u64, stop: u64) {
+        self.runtime(start, stop);
+        let count = self.errors.entry(error.kind()).or_insert(0);
+        *count += 1;
+        let count = *count;
+        record_counter(&self.recorder, self.error_label(error.kind()), stop, count);
+    }
+
+    /// Record a call to `sample()` which ran past the configured timeout
+    pub fn timeout(&mut self, stop: u64) {
+        self.timeouts += 1;
+        record_counter(&self.recorder, self.label("timeouts"), stop, self.timeouts);
+    }
+
+    /// Remove the health metrics for the sampler
+    pub fn deregister(&self) {
+        delete_distribution(&self.recorder, self.label("runtime"));
+        for metric in &["timeouts", "last_success"] {
+            delete_channel(&self.recorder, self.label(metric));
+        }
+        for kind in ERROR_KINDS {
+            delete_channel(&self.recorder, self.error_label(kind));
+        }
+    }
+
+    fn register(&self, config: &Config) {
+        register_distribution(
+            &self.recorder,
+            Metric::new(
+                self.label("runtime"),
+                "time, in nanoseconds, taken by each sample",
+            )
+            .with_unit(Unit::Nanoseconds),
+            MINUTE,
+            2,
+            config.general().window(),
+            PERCENTILES,
+        );
+        register_value(
+            &self.recorder,
+            Metric::new(
+                self.label("timeouts"),
+                "number of samples which ran past the configured timeout",
+            ),
+            Source::Counter,
+        );
+        for kind in ERROR_KINDS {
+            register_value(
+                &self.recorder,
+                Metric::new(
+                    self.error_label(kind),
+                    format!("number of samples which returned a {} error", kind),
+                ),
+                Source::Counter,
+            );
+        }
+        register_value(
+            &self.recorder,
+            Metric::new(
+                self.label("last_success"),
+                "time, in seconds since the UNIX epoch, of the last successful sample",
+            )
+            .with_unit(Unit::Seconds),
+            Source::Gauge,
+        );
+    }
+
+    fn runtime(&self, start: u64, stop: u64) {
+        record_distribution(&self.recorder, self.label("runtime"), stop, stop - start, 1);
+    }
+
+    fn label(&self, metric: &str) -> String {
+        format!("rezolus/sampler/{}/{}", self.name, metric)
+    }
+
+    fn error_label(&self, kind: &str) -> String {
+        format!("rezolus/sampler/{}/errors/{}", self.name, kind)
+    }
+}
@@ -0,0 +1,260 @@
+// Copyright 2019 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+mod health;
+mod worker;
+
+use self::worker::{Backoff, Worker};
+
+use crate::common::{MILLISECOND, POLL_DELAY};
+use crate::config::Config;
+use crate::samplers::Registration;
+use crate::stats::{delete_channel, record_counter, record_gauge, register_value, Metric};
+
+use logger::*;
+use metrics::*;
+
+use std::fmt;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// The state of a `Sampler`, published as `rezolus/sampler/(name)/state`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Status {
+    /// the sampler is not enabled, or has been stopped
+    Disabled = 0,
+    /// the sampler is being constructed or is sampling
+    Running = 1,
+    /// the sampler has failed and is waiting to be restarted
+    BackingOff = 2,
+}
+
+impl From<u64> for Status {
+    fn from(value: u64) -> Self {
+        match value {
+            1 => Status::Running,
+            2 => Status::BackingOff,
+            _ => Status::Disabled,
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Status::Disabled => write!(f, "disabled"),
+            Status::Running => write!(f, "running"),
+            Status::BackingOff => write!(f, "backing_off"),
+        }
+    }
+}
+
+/// A `Sampler` known to the `Scheduler` and its current worker, if any
+struct Entry {
+    registration: Registration,
+    worker: Option<Worker>,
+    /// a worker which was abandoned while blocked. its thread may still be
+    /// running, so no new worker is started until it exits
+    abandoned: Option<Worker>,
+    /// time at which the worker should be replaced, once it has stopped
+    restart: Option<u64>,
+    /// the backoff before restarting the sampler, shared with its worker
+    backoff: Arc<Backoff>,
+}
+
+/// The `Scheduler` runs each `Sampler` on a dedicated worker thread, so that
+/// a slow or blocked sampler only delays itself while the rest keep their
+/// cadence. It also supervises the workers, counting deadlines missed by
+/// calls which run past the configured timeout and abandoning workers which
+/// stop making progress. Samplers which fail are restarted with an
+/// exponential backoff.
+pub struct Scheduler {
+    config: Arc<Config>,
+    recorder: Recorder<AtomicU32>,
+    entries: Vec<Entry>,
+}
+
+impl Scheduler {
+    pub fn new(config: Arc<Config>, recorder: Recorder<AtomicU32>) -> Self {
+        Self {
+            config,
+            recorder,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Add a registered `Sampler` and spawn a worker to construct and sample
+    /// it
+    pub fn add(&mut self, registration: Registration) {
+        let name = registration.name;
+        let mut entry = Entry {
+            registration,
+            worker: None,
+            abandoned: None,
+            restart: None,
+            backoff: Arc::new(Backoff::default()),
+        };
+        register_value(
+            &self.recorder,
+            Metric::new(
+                state_label(name),
+                "state of the sampler: 0 when disabled, 1 when running, and 2 when backing off",
+            ),
+            Source::Gauge,
+        );
+        start(&self.config, &self.recorder, &mut entry);
+        self.entries.push(entry);
+    }
+
+    /// Apply a new config. Samplers whose config section has changed are
+    /// stopped, which deregisters their metrics, and then constructed again
+    /// with the new config. Samplers which are now disabled will not be
+    /// restarted and samplers which are now enabled will be started.
+    pub fn reload(&mut self, config: Arc<Config>) {
+        let general = self.config.changed(&config, "general");
+        for entry in self.entries.iter_mut() {
+            if general || self.config.changed(&config, entry.registration.section) {
+                debug!("config changed for sampler {}", entry.registration.name);
+                if let Some(ref worker) = entry.worker {
+                    worker.stop();
+                }
+                entry.restart = Some(time::precise_time_ns());
+            }
+        }
+        self.config = config;
+    }
+
+    /// Check on each worker, counting missed deadlines for calls which have
+    /// run past the timeout. Workers which have stopped sampling are reaped
+    /// and workers which have been blocked for `max_timeouts` intervals are
+    /// abandoned and restarted after a backoff. Workers pending a restart are
+    /// started once the previous worker, including an abandoned one, has
+    /// stopped, so a sampler which stays blocked holds at most two threads.
+    pub fn supervise(&mut self) {
+        let now = time::precise_time_ns();
+        let timeout = self.config.general().timeout() as u64 * MILLISECOND;
+        let max_timeouts = self.config.general().max_timeouts() as u64;
+
+        for entry in self.entries.iter_mut() {
+            let name = entry.registration.name;
+            if let Some(abandoned) = entry.abandoned.take() {
+                if abandoned.is_finished() {
+                    debug!("abandoned worker for sampler {} has exited", name);
+                    abandoned.join();
+                } else {
+                    entry.abandoned = Some(abandoned);
+                }
+            }
+            if let Some(worker) = entry.worker.take() {
+                if worker.is_finished() {
+                    delete_channel(&self.recorder, missed_label(name));
+                    worker.join();
+                } else if let Some(elapsed) = worker.running_for(now) {
+                    if elapsed > timeout && worker.mark_overdue() {
+                        debug!("sampler {} has run past its deadline", name);
+                    }
+                    if elapsed >= max_timeouts * worker.interval() {
+                        warn!(
+                            "Sampler {} has been blocked for {} ms. Abandoning the sampler",
+                            name,
+                            elapsed / MILLISECOND
+                        );
+                        delete_channel(&self.recorder, missed_label(name));
+                        worker.abandon();
+                        entry.abandoned = Some(worker);
+                        entry.restart = Some(now + entry.backoff.next(&self.config));
+                    } else {
+                        entry.worker = Some(worker);
+                    }
+                } else {
+                    entry.worker = Some(worker);
+                }
+            }
+            if entry.worker.is_none() && entry.abandoned.is_none() {
+                if let Some(restart) = entry.restart {
+                    if now >= restart {
+                        start(&self.config, &self.recorder, entry);
+                    }
+                }
+            }
+            let status = if let Some(ref worker) = entry.worker {
+                record_counter(&self.recorder, missed_label(name), now, worker.missed());
+                worker.status()
+            } else if entry.restart.is_some() {
+                Status::BackingOff
+            } else {
+                Status::Disabled
+            };
+            record_gauge(&self.recorder, state_label(name), now, status as u64);
+        }
+    }
+
+    /// Stop every worker, waiting up to `timeout` nanoseconds for them to
+    /// deregister their samplers. Workers which are still blocked after the
+    /// timeout are abandoned
+    pub fn shutdown(&mut self, timeout: u64) {
+        for entry in self.entries.iter() {
+            if let Some(ref worker) = entry.worker {
+                worker.stop();
+            }
+        }
+        let deadline = time::precise_time_ns() + timeout;
+        for entry in self.entries.iter_mut() {
+            entry.restart = None;
+            if let Some(worker) = entry.worker.take() {
+                while !worker.is_finished() && time::precise_time_ns() < deadline {
+                    thread::sleep(Duration::from_nanos(POLL_DELAY));
+                }
+                if worker.is_finished() {
+                    worker.join();
+                } else {
+                    warn!(
+                        "Sampler {} did not stop within {} ms. Abandoning the sampler",
+                        entry.registration.name,
+                        timeout / MILLISECOND
+                    );
+                    worker.abandon();
+                }
+            }
+        }
+    }
+}
+
+/// Spawn a new worker for the `Entry`. If the worker can not be spawned, it
+/// is retried after a backoff
+fn start(config: &Arc<Config>, recorder: &Recorder<AtomicU32>, entry: &mut Entry) {
+    let name = entry.registration.name;
+    match Worker::spawn(
+        entry.registration,
+        config.clone(),
+        recorder.clone(),
+        entry.backoff.clone(),
+    ) {
+        Ok(worker) => {
+            register_value(
+                recorder,
+                Metric::new(
+                    missed_label(name),
+                    "number of samples which ran past the timeout or were skipped",
+                ),
+                Source::Counter,
+            );
+            entry.worker = Some(worker);
+            entry.restart = None;
+        }
+        Err(e) => {
+            error!("failed to spawn worker for sampler {}: {}", name, e);
+            entry.restart = Some(time::precise_time_ns() + entry.backoff.next(config));
+        }
+    }
+}
+
+fn missed_label(name: &str) -> String {
+    format!("rezolus/sampler/{}/missed_deadlines", name)
+}
+
+fn state_label(name: &str) -> String {
+    format!("rezolus/sampler/{}/state", name)
+}
@@ -0,0 +1,343 @@
+// Copyright 2019 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use crate::common::MILLISECOND;
+use crate::config::Config;
+use crate::samplers::{Registration, Sampler};
+
+use super::health::Health;
+use super::Status;
+
+use logger::*;
+use metrics::*;
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Number of consecutive successful samples after which a sampler is
+/// considered healthy and its backoff is reset
+const HEALTHY_SAMPLES: u64 = 10;
+
+/// The delay before a failed sampler is constructed again. It is kept by the
+/// `Scheduler` for each sampler, so that it keeps growing across workers,
+/// including when a blocked worker is abandoned and replaced
+#[derive(Default)]
+pub struct Backoff {
+    /// the next delay in nanoseconds, zero for the configured `backoff`
+    next: AtomicU64,
+}
+
+impl Backoff {
+    /// Returns the delay before the next restart, doubling the delay for the
+    /// one after up to the configured `max_backoff`
+    pub fn next(&self, config: &Config) -> u64 {
+        let initial = config.general().backoff() as u64 * MILLISECOND;
+        let max = config.general().max_backoff() as u64 * MILLISECOND;
+        let current = match self.next.load(Ordering::Relaxed) {
+            0 => initial,
+            next => std::cmp::min(next, max),
+        };
+        self.next
+            .store(std::cmp::min(current * 2, max), Ordering::Relaxed);
+        current
+    }
+
+    /// Return to the configured `backoff` once the sampler is healthy
+    pub fn reset(&self) {
+        self.next.store(0, Ordering::Relaxed);
+    }
+}
+
+/// State shared between a worker thread and the `Scheduler`
+#[derive(Default)]
+struct State {
+    /// interval between samples in nanoseconds
+    interval: AtomicU64,
+    /// start time of the in-flight call to `sample()`, zero when idle
+    started: AtomicU64,
+    /// set once the sampler has completed its first call to `sample()`,
+    /// which registers its metrics and is exempt from the timeout
+    sampled: AtomicBool,
+    /// set once the in-flight call has been counted as a missed deadline
+    overdue: AtomicBool,
+    /// number of deadlines missed by the sampler
+    missed: AtomicU64,
+    /// set by the `Scheduler` to ask the worker to stop sampling
+    cancelled: AtomicBool,
+    /// set by the worker once it has stopped sampling and removed its
+    /// metrics
+    finished: AtomicBool,
+    /// the `Status` of the sampler
+    status: AtomicU64,
+}
+
+/// A `Worker` owns the thread on which a single `Sampler` is constructed and
+/// sampled on its own cadence
+pub struct Worker {
+    name: String,
+    state: Arc<State>,
+    thread: JoinHandle<()>,
+}
+
+impl Worker {
+    /// Spawn a thread which constructs the registered `Sampler` and samples
+    /// it until the worker is cancelled. A sampler which fails is constructed
+    /// again after an exponential backoff
+    pub fn spawn(
+        registration: Registration,
+        config: Arc<Config>,
+        recorder: Recorder<AtomicU32>,
+        backoff: Arc<Backoff>,
+    ) -> Result<Self, std::io::Error> {
+        let state = Arc::new(State::default());
+        let thread = {
+            let state = state.clone();
+            thread::Builder::new()
+                .name(registration.name.to_string())
+                .spawn(move || run(registration, config, recorder, &backoff, &state))?
+        };
+        Ok(Self {
+            name: registration.name.to_string(),
+            state,
+            thread,
+        })
+    }
+
+    /// Interval between samples in nanoseconds, zero until the sampler has
+    /// been constructed
+    pub fn interval(&self) -> u64 {
+        self.state.interval.load(Ordering::Relaxed)
+    }
+
+    /// Number of deadlines the sampler has missed
+    pub fn missed(&self) -> u64 {
+        self.state.missed.load(Ordering::Relaxed)
+    }
+
+    /// The current `Status` of the sampler
+    pub fn status(&self) -> Status {
+        Status::from(self.state.status.load(Ordering::Relaxed))
+    }
+
+    /// True once the worker has stopped sampling and may be joined
+    pub fn is_finished(&self) -> bool {
+        self.state.finished.load(Ordering::Relaxed)
+    }
+
+    /// Returns how long the in-flight call to `sample()` has been running.
+    /// The first call is not reported, as it also registers the sampler
+    pub fn running_for(&self, now: u64) -> Option<u64> {
+        let started = self.state.started.load(Ordering::Relaxed);
+        if started == 0 || !self.state.sampled.load(Ordering::Relaxed) {
+            None
+        } else {
+            Some(now.saturating_sub(started))
+        }
+    }
+
+    /// Count the in-flight call as a missed deadline. Returns false if it has
+    /// already been counted
+    pub fn mark_overdue(&self) -> bool {
+        if self.state.overdue.swap(true, Ordering::Relaxed) {
+            false
+        } else {
+            self.state.missed.fetch_add(1, Ordering::Relaxed);
+            true
+        }
+    }
+
+    /// Ask the worker to stop sampling. A worker waiting for its next tick
+    /// is woken immediately, otherwise it stops once the in-flight call to
+    /// `sample()` returns
+    pub fn stop(&self) {
+        self.state.cancelled.store(true, Ordering::Relaxed);
+        self.thread.thread().unpark();
+    }
+
+    /// Stop the worker without waiting for the in-flight call. The thread
+    /// removes the sampler's metrics and exits whenever the call returns, and
+    /// the worker should be kept until then so it may be joined. No worker
+    /// may replace it before then, as the metrics are still its own
+    pub fn abandon(&self) {
+        self.stop();
+    }
+
+    /// Wait for a finished worker thread to exit
+    pub fn join(self) {
+        if self.thread.join().is_err() {
+            error!("worker thread for sampler {} panicked", self.name);
+        }
+    }
+}
+
+fn run(
+    registration: Registration,
+    config: Arc<Config>,
+    recorder: Recorder<AtomicU32>,
+    backoff: &Backoff,
+    state: &State,
+) {
+    let name = registration.name;
+    let mut health = Health::new(name, &config, recorder.clone());
+
+    loop {
+        state
+            .status
+            .store(Status::Running as u64, Ordering::Relaxed);
+        match (registration.new)(config.clone(), recorder.clone()) {
+            Ok(Some(mut sampler)) => {
+                let interval = config.interval((registration.config)(&config)) as u64 * MILLISECOND;
+                drive(
+                    name,
+                    &config,
+                    &mut *sampler,
+                    interval,
+                    &mut health,
+                    backoff,
+                    state,
+                );
+                sampler.deregister();
+            }
+            Ok(None) => {
+                debug!("sampler {} is not enabled", name);
+                state
+                    .status
+                    .store(Status::Disabled as u64, Ordering::Relaxed);
+                break;
+            }
+            Err(e) => {
+                error!("failed to initialize sampler {}: {}", name, e);
+            }
+        }
+
+        if state.cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+
+        let delay = backoff.next(&config);
+        warn!(
+            "Sampler {} will be restarted in {} ms",
+            name,
+            delay / MILLISECOND
+        );
+        state.interval.store(0, Ordering::Relaxed);
+        state.sampled.store(false, Ordering::Relaxed);
+        state
+            .status
+            .store(Status::BackingOff as u64, Ordering::Relaxed);
+        if !wait_until(state, time::precise_time_ns() + delay) {
+            break;
+        }
+    }
+
+    health.deregister();
+    state.finished.store(true, Ordering::Relaxed);
+}
+
+/// Sample every `interval` nanoseconds until the sampler fails or the worker
+/// is stopped. The backoff is reset once the sampler has taken
+/// `HEALTHY_SAMPLES` consecutive successful samples
+fn drive(
+    name: &str,
+    config: &Config,
+    sampler: &mut dyn Sampler,
+    interval: u64,
+    health: &mut Health,
+    backoff: &Backoff,
+    state: &State,
+) {
+    let timeout = config.general().timeout() as u64 * MILLISECOND;
+    let max_timeouts = config.general().max_timeouts();
+
+    state.interval.store(interval, Ordering::Relaxed);
+
+    let mut healthy = 0;
+    let mut sequential_timeouts = 0;
+    let mut first_run = true;
+    let mut next = time::precise_time_ns();
+
+    loop {
+        // wait for the next tick, waking early if the worker is stopped
+        if !wait_until(state, next) {
+            break;
+        }
+
+        let start = time::precise_time_ns();
+        state.started.store(start, Ordering::Relaxed);
+        let result = sampler.sample();
+        let stop = time::precise_time_ns();
+        state.started.store(0, Ordering::Relaxed);
+        let counted = state.overdue.swap(false, Ordering::Relaxed);
+
+        if state.cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+
+        match result {
+            Ok(()) => {
+                health.success(start, stop);
+                healthy += 1;
+                if healthy == HEALTHY_SAMPLES {
+                    backoff.reset();
+                }
+            }
+            Err(ref e) if e.is_fatal() => {
+                health.error(e, start, stop);
+                warn!("Sampler {} returned a {}. Failing the sampler", name, e);
+                break;
+            }
+            Err(ref e) => {
+                health.error(e, start, stop);
+                debug!("Sampler {} returned a {}", name, e);
+                healthy = 0;
+            }
+        }
+
+        if !first_run && stop - start > timeout {
+            health.timeout(stop);
+            healthy = 0;
+            if !counted {
+                state.missed.fetch_add(1, Ordering::Relaxed);
+            }
+            sequential_timeouts += 1;
+            if sequential_timeouts >= max_timeouts {
+                warn!(
+                    "Sampler {} took over {} ms {} times sequentially. Failing the sampler",
+                    name,
+                    config.general().timeout(),
+                    sequential_timeouts
+                );
+                break;
+            }
+        } else {
+            sequential_timeouts = 0;
+        }
+        first_run = false;
+        state.sampled.store(true, Ordering::Relaxed);
+
+        // stay on the sampler's cadence. any ticks which elapsed while the
+        // sampler was running are skipped and count as missed deadlines
+        next += interval;
+        while next <= stop {
+            next += interval;
+            state.missed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Park until the deadline, returning false if the worker is stopped first
+fn wait_until(state: &State, deadline: u64) -> bool {
+    loop {
+        if state.cancelled.load(Ordering::Relaxed) {
+            return false;
+        }
+        let now = time::precise_time_ns();
+        if now >= deadline {
+            return true;
+        }
+        thread::park_timeout(Duration::from_nanos(deadline - now));
+    }
+}
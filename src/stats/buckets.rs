@@ -0,0 +1,181 @@
+// Copyright 2019 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use lazy_static::lazy_static;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+lazy_static! {
+    static ref BUCKETS: RwLock<HashMap<String, Arc<Tracker>>> = RwLock::new(HashMap::new());
+}
+
+/// Tracks the bucket counts of a distribution channel. The counts are atomic,
+/// so values are recorded under a read lock and samplers on different
+/// threads do not contend with each other
+struct Tracker {
+    bounds: Vec<u64>,
+    // one more than the bounds, the last bucket counts values above the max
+    counts: Vec<AtomicU64>,
+    count: AtomicU64,
+    sum: AtomicU64,
+}
+
+impl Tracker {
+    fn new(max: u64) -> Self {
+        let bounds = bounds(max);
+        let counts = (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect();
+        Self {
+            bounds,
+            counts,
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0),
+        }
+    }
+
+    fn record(&self, value: u64, count: u64) {
+        let index = match self.bounds.binary_search(&value) {
+            Ok(index) | Err(index) => index,
+        };
+        self.counts[index].fetch_add(count, Ordering::Relaxed);
+        self.count.fetch_add(count, Ordering::Relaxed);
+        // fetch_add wraps on overflow
+        self.sum
+            .fetch_add(value.wrapping_mul(count), Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> Buckets {
+        Buckets {
+            bounds: self.bounds.clone(),
+            counts: self
+                .counts
+                .iter()
+                .map(|count| count.load(Ordering::Relaxed))
+                .collect(),
+            count: self.count.load(Ordering::Relaxed),
+            sum: self.sum.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Cumulative bucket counts for a distribution channel. The upper bounds of
+/// the buckets follow a 1-2-5 series up to the maximum value of the channel,
+/// so they are the same on every host and can be merged across a fleet
+#[derive(Clone, Debug)]
+pub struct Buckets {
+    bounds: Vec<u64>,
+    // one more than the bounds, the last bucket counts values above the max
+    counts: Vec<u64>,
+    count: u64,
+    sum: u64,
+}
+
+impl Buckets {
+    /// Pairs of the upper bound of each bucket, `None` for the bucket above
+    /// the max, and the number of values in the bucket
+    pub fn buckets(&self) -> Vec<(Option<u64>, u64)> {
+        self.bounds
+            .iter()
+            .map(|bound| Some(*bound))
+            .chain(Some(None))
+            .zip(self.counts.iter().cloned())
+            .collect()
+    }
+
+    /// The total number of values recorded
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The sum of the values recorded
+    pub fn sum(&self) -> u64 {
+        self.sum
+    }
+}
+
+/// Upper bounds of the buckets from 1 to `max`: 1, 2, 5, 10, 20, 50, ...
+fn bounds(max: u64) -> Vec<u64> {
+    let mut bounds = Vec::new();
+    let mut power: u64 = 1;
+    loop {
+        for multiple in &[1, 2, 5] {
+            match power.checked_mul(*multiple) {
+                Some(bound) if bound <= max => bounds.push(bound),
+                _ => return bounds,
+            }
+        }
+        power = match power.checked_mul(10) {
+            Some(power) => power,
+            None => return bounds,
+        };
+    }
+}
+
+/// Start tracking buckets for a label, discarding any previous counts
+pub fn register(label: &str, max: u64) {
+    let mut buckets = BUCKETS.write().unwrap();
+    buckets.insert(label.to_string(), Arc::new(Tracker::new(max)));
+}
+
+/// Stop tracking buckets for a label
+pub fn deregister(label: &str) {
+    let mut buckets = BUCKETS.write().unwrap();
+    buckets.remove(label);
+}
+
+/// Record `count` occurrences of `value` if the label is being tracked
+pub fn record(label: &str, value: u64, count: u64) {
+    let buckets = BUCKETS.read().unwrap();
+    if let Some(tracker) = buckets.get(label) {
+        tracker.record(value, count);
+    }
+}
+
+/// Returns a copy of the buckets for each tracked label, sorted by label
+pub fn snapshot() -> Vec<(String, Buckets)> {
+    let buckets = BUCKETS.read().unwrap();
+    let mut snapshot: Vec<(String, Buckets)> = buckets
+        .iter()
+        .map(|(label, tracker)| (label.clone(), tracker.snapshot()))
+        .collect();
+    snapshot.sort_by(|a, b| a.0.cmp(&b.0));
+    snapshot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_bounds() {
+        assert_eq!(bounds(0), Vec::<u64>::new());
+        assert_eq!(bounds(1), vec![1]);
+        assert_eq!(bounds(100), vec![1, 2, 5, 10, 20, 50, 100]);
+        assert_eq!(bounds(499), vec![1, 2, 5, 10, 20, 50, 100, 200]);
+        assert_eq!(bounds(u64::MAX).len(), 58);
+    }
+
+    #[test]
+    fn record_values() {
+        let tracker = Tracker::new(10);
+        tracker.record(0, 1);
+        tracker.record(2, 3);
+        tracker.record(3, 1);
+        tracker.record(11, 2);
+        let buckets = tracker.snapshot();
+        assert_eq!(
+            buckets.buckets(),
+            vec![
+                (Some(1), 1),
+                (Some(2), 3),
+                (Some(5), 1),
+                (Some(10), 0),
+                (None, 2)
+            ]
+        );
+        assert_eq!(buckets.count(), 7);
+        assert_eq!(buckets.sum(), 31);
+    }
+}
@@ -0,0 +1,253 @@
+// Copyright 2019 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use super::labels::parse;
+use super::metadata::{metadata, Kind, Unit};
+use super::offset_ms;
+
+use metrics::*;
+use time::Timespec;
+
+use std::collections::BTreeMap;
+
+/// The type of a metric family
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Type {
+    Counter,
+    Gauge,
+    Summary,
+    Unknown,
+}
+
+/// A metric family, which shares a single `# HELP` and `# TYPE`
+pub struct Family {
+    pub kind: Type,
+    pub help: String,
+    pub unit: Option<Unit>,
+    pub samples: Vec<Sample>,
+}
+
+/// A sample within a family, identified by its labels and, for summaries,
+/// its quantile
+pub struct Sample {
+    pub labels: Vec<(String, String)>,
+    pub quantile: Option<f64>,
+    pub value: u64,
+    /// the time at which the channel was registered
+    pub created: Option<Timespec>,
+}
+
+impl Sample {
+    /// Formats the labels of the sample, including its quantile
+    pub fn labels(&self) -> String {
+        match self.quantile {
+            Some(quantile) => {
+                format_labels(&self.labels, Some(("quantile", &quantile.to_string())))
+            }
+            None => format_labels(&self.labels, None),
+        }
+    }
+}
+
+/// Formats labels, followed by an `extra` label such as the quantile, as
+/// `{key="value",...}`. Empty if there are no labels
+pub fn format_labels(labels: &[(String, String)], extra: Option<(&str, &str)>) -> String {
+    let mut labels: Vec<String> = labels
+        .iter()
+        .map(|(key, value)| format!("{}=\"{}\"", sanitize(key), escape_value(value)))
+        .collect();
+    if let Some((key, value)) = extra {
+        labels.push(format!("{}=\"{}\"", key, escape_value(value)));
+    }
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", labels.join(","))
+    }
+}
+
+/// Groups the readings into families keyed by name. Counters and gauges are
+/// named for the metric name of their channel, with any characters which are
+/// not valid in a metric name replaced by `_`, and keep the labels of the
+/// channel. Percentiles are grouped into a summary with quantiles:
+/// named `(name)_histogram` for counters and gauges, and by the name alone for
+/// distributions. The samples of each family are sorted by labels and quantile
+pub fn families<'a, I>(readings: I) -> BTreeMap<String, Family>
+where
+    I: Iterator<Item = (&'a str, Output, u64)>,
+{
+    let mut families: BTreeMap<String, Family> = BTreeMap::new();
+    for (label, output, value) in readings {
+        let metadata = metadata(label);
+        let kind = metadata.as_ref().map(|metadata| metadata.kind());
+        let unit = metadata.as_ref().and_then(|metadata| metadata.unit());
+        let created = metadata.as_ref().map(|metadata| metadata.created());
+        let (metric, labels) = parse(label);
+        let description = metadata
+            .as_ref()
+            .map(|metadata| metadata.description())
+            .unwrap_or(metric);
+        let name = sanitize(metric);
+        let (name, family, quantile, value) = match output {
+            Output::Counter => {
+                let family = Family {
+                    kind: match kind {
+                        Some(Kind::Counter) => Type::Counter,
+                        Some(Kind::Gauge) => Type::Gauge,
+                        _ => Type::Unknown,
+                    },
+                    help: description.to_string(),
+                    unit,
+                    samples: Vec::new(),
+                };
+                (name, family, None, value)
+            }
+            Output::Percentile(percentile) => {
+                let quantile = match quantile(percentile) {
+                    Some(quantile) => quantile,
+                    None => continue,
+                };
+                // the percentiles of a counter are of its secondly rate, which
+                // does not share the unit of the counter
+                let (name, help, unit) = match kind {
+                    Some(Kind::Distribution) => (name, description.to_string(), unit),
+                    Some(Kind::Counter) => (
+                        format!("{}_histogram", name),
+                        format!("{} (percentiles of the secondly rate)", description),
+                        None,
+                    ),
+                    _ => (
+                        format!("{}_histogram", name),
+                        format!("{} (percentiles)", description),
+                        unit,
+                    ),
+                };
+                let family = Family {
+                    kind: Type::Summary,
+                    help,
+                    unit,
+                    samples: Vec::new(),
+                };
+                (name, family, Some(quantile), value)
+            }
+            Output::MaxPointTime => {
+                let family = Family {
+                    kind: Type::Gauge,
+                    help: format!(
+                        "offset, in milliseconds, into the minute at which the maximum of {} occurred",
+                        metric
+                    ),
+                    unit: None,
+                    samples: Vec::new(),
+                };
+                (
+                    format!("{}_maximum_offset_ms", name),
+                    family,
+                    None,
+                    offset_ms(value),
+                )
+            }
+            _ => {
+                continue;
+            }
+        };
+        let created = match output {
+            Output::MaxPointTime => None,
+            _ => created,
+        };
+        families.entry(name).or_insert(family).samples.push(Sample {
+            labels,
+            quantile,
+            value,
+            created,
+        });
+    }
+    for family in families.values_mut() {
+        family.samples.sort_by(|a, b| {
+            a.labels.cmp(&b.labels).then(
+                a.quantile
+                    .partial_cmp(&b.quantile)
+                    .unwrap_or(std::cmp::Ordering::Equal),
+            )
+        });
+    }
+    families
+}
+
+/// Converts a label into a valid metric name
+pub fn sanitize(label: &str) -> String {
+    let mut name: String = label
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// Escapes help text as required by the text formats
+pub fn escape(help: &str) -> String {
+    help.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+/// Escapes a label value as required by the text formats
+fn escape_value(value: &str) -> String {
+    escape(value).replace('"', "\\\"")
+}
+
+/// Returns the quantile for a `Percentile`. Percentiles are named for their
+/// digits with the decimal point after the first two, and a leading zero for
+/// those below one percent: `p1` is 0.01, `p999` is 0.999, `p01` is 0.001
+fn quantile(percentile: Percentile) -> Option<f64> {
+    match percentile {
+        Percentile::Minimum => Some(0.0),
+        Percentile::Maximum => Some(1.0),
+        percentile => {
+            let name = percentile.to_string();
+            let digits = name.trim_start_matches('p');
+            if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            let quantile = if let Some(digits) = digits.strip_prefix('0') {
+                format!("0.00{}", digits)
+            } else if digits.len() == 1 {
+                format!("0.0{}", digits)
+            } else {
+                format!("0.{}", digits)
+            };
+            quantile.parse().ok()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_label() {
+        assert_eq!(sanitize("cpu/user"), "cpu_user");
+        assert_eq!(sanitize("ebpf::block/size"), "ebpf__block_size");
+        assert_eq!(sanitize("network/eth0.100"), "network_eth0_100");
+        assert_eq!(sanitize("5xx"), "_5xx");
+    }
+
+    #[test]
+    fn percentile_quantile() {
+        assert_eq!(quantile(Percentile::Minimum), Some(0.0));
+        assert_eq!(quantile(Percentile::p01), Some(0.001));
+        assert_eq!(quantile(Percentile::p1), Some(0.01));
+        assert_eq!(quantile(Percentile::p10), Some(0.1));
+        assert_eq!(quantile(Percentile::p50), Some(0.5));
+        assert_eq!(quantile(Percentile::p999), Some(0.999));
+        assert_eq!(quantile(Percentile::Maximum), Some(1.0));
+    }
+}
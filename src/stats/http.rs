@@ -2,11 +2,14 @@
 // Licensed under the Apache License, Version 2.0
 // http://www.apache.org/licenses/LICENSE-2.0
 
+use super::labels::join;
+use super::{buckets, offset_ms, openmetrics, prometheus};
 use crate::common::MILLISECOND;
+use crate::scheduler::Status;
 
 use logger::*;
 use metrics::*;
-use tiny_http::{Method, Response, Server};
+use tiny_http::{Header, Method, Response, Server};
 
 use std::net::SocketAddr;
 
@@ -58,8 +61,30 @@ impl Http {
                         )));
                     }
                     "/metrics" => {
-                        debug!("Serving Prometheus compatible stats");
-                        let _ = request.respond(Response::from_string(self.prometheus()));
+                        let openmetrics = request.headers().iter().any(|header| {
+                            header.field.equiv("Accept")
+                                && header.value.as_str().contains(openmetrics::MEDIA_TYPE)
+                        });
+                        let (content, content_type) = if openmetrics {
+                            debug!("Serving OpenMetrics stats");
+                            (self.openmetrics(), openmetrics::CONTENT_TYPE)
+                        } else {
+                            debug!("Serving Prometheus compatible stats");
+                            (self.prometheus(), prometheus::CONTENT_TYPE)
+                        };
+                        let header = Header::from_bytes("Content-Type", content_type).unwrap();
+                        let _ = request.respond(Response::from_string(content).with_header(header));
+                    }
+                    "/histograms" => {
+                        debug!("Serving Prometheus compatible histograms");
+                        let header =
+                            Header::from_bytes("Content-Type", prometheus::CONTENT_TYPE).unwrap();
+                        let content = prometheus::encode_buckets(&buckets::snapshot());
+                        let _ = request.respond(Response::from_string(content).with_header(header));
+                    }
+                    "/histograms.json" => {
+                        debug!("Serving machine readable histograms");
+                        let _ = request.respond(Response::from_string(self.buckets()));
                     }
                     "/metrics.json" | "/vars.json" | "/admin/metrics.json" => {
                         debug!("Serving machine readable stats");
@@ -69,6 +94,10 @@ impl Http {
                         debug!("Serving human readable stats");
                         let _ = request.respond(Response::from_string(self.human()));
                     }
+                    "/samplers" => {
+                        debug!("Serving sampler status");
+                        let _ = request.respond(Response::from_string(self.samplers()));
+                    }
                     url => {
                         debug!("GET on non-existent url: {}", url);
                         debug!("Serving machine readable stats");
@@ -84,59 +113,59 @@ impl Http {
         std::thread::sleep(std::time::Duration::from_millis(1));
     }
 
+    /// Reports the readings in the Prometheus text exposition format
     pub fn prometheus(&self) -> String {
+        prometheus::encode(&self.snapshot)
+    }
+
+    /// Reports the readings in the OpenMetrics text exposition format
+    pub fn openmetrics(&self) -> String {
+        openmetrics::encode(&self.snapshot)
+    }
+
+    /// Reports the buckets of each distribution channel as JSON. Only buckets
+    /// which have counts are included, as pairs of their upper bound and count
+    pub fn buckets(&self) -> String {
+        let mut data = Vec::new();
+        for (label, buckets) in buckets::snapshot() {
+            let counts: Vec<String> = buckets
+                .buckets()
+                .iter()
+                .filter(|(_, count)| *count > 0)
+                .map(|(bound, count)| match bound {
+                    Some(bound) => format!("[{},{}]", bound, count),
+                    None => format!("[\"+Inf\",{}]", count),
+                })
+                .collect();
+            data.push(format!(
+                "{}:{{\"count\":{},\"sum\":{},\"buckets\":[{}]}}",
+                quote(&label),
+                buckets.count(),
+                buckets.sum(),
+                counts.join(",")
+            ));
+        }
+        format!("{{{}}}", data.join(","))
+    }
+
+    /// Reports the `Status` of each sampler, one per line
+    pub fn samplers(&self) -> String {
+        let prefix = "rezolus/sampler/";
+        let suffix = "/state";
         let mut data = Vec::new();
         for reading in &self.snapshot {
             let label = reading.label();
-            let output = reading.output();
-            let value = reading.value();
-            match output {
-                Output::Counter => {
-                    if let Some(ref count_label) = self.count_label {
-                        data.push(format!("{}/{} {}", label, count_label, value));
-                    } else {
-                        data.push(format!("{} {}", label, value));
-                    }
-                }
-                Output::Percentile(percentile) => match percentile {
-                    Percentile::Minimum => {
-                        data.push(format!("{}/minimum/value {}", label, value));
-                    }
-                    Percentile::Maximum => {
-                        data.push(format!("{}/maximum/value {}", label, value));
-                    }
-                    _ => {
-                        data.push(format!("{}/histogram/{} {}", label, percentile, value));
-                    }
-                },
-                Output::MaxPointTime => {
-                    // we have point's ns since X and current timespec and current ns sinc X
-                    let point_ns = value;
-                    let now_timespec = time::get_time();
-                    let now_ns = time::precise_time_ns();
-
-                    // find the number of NS in the past for point
-                    let delta_ns = now_ns - point_ns;
-                    let point_timespec =
-                        now_timespec - time::Duration::nanoseconds(delta_ns as i64);
-
-                    // convert to UTC
-                    let point_utc = time::at_utc(point_timespec);
-                    // calculate offset from the top of the minute
-                    let offset = point_utc.tm_sec as u64 * 1_000_000_000 + point_utc.tm_nsec as u64;
-                    let offset_ms = (offset as f64 / 1_000_000.0).floor() as u64;
-                    data.push(format!("{}/maximum/offset_ms {}", label, offset_ms));
-                }
-                _ => {
-                    continue;
+            if let Output::Counter = reading.output() {
+                if label.starts_with(prefix) && label.ends_with(suffix) {
+                    let name = &label[prefix.len()..(label.len() - suffix.len())];
+                    data.push(format!("{}: {}", name, Status::from(reading.value())));
                 }
             }
         }
         data.sort();
         let mut content = data.join("\n");
         content += "\n";
-        let parts: Vec<&str> = content.split('/').collect();
-        parts.join("_")
+        content
     }
 
     pub fn human(&self) -> String {
@@ -148,39 +177,32 @@ impl Http {
             match output {
                 Output::Counter => {
                     if let Some(ref count_label) = self.count_label {
-                        data.push(format!("{}/{}: {}", label, count_label, value));
+                        data.push(format!("{}: {}", join(label, count_label), value));
                     } else {
                         data.push(format!("{}: {}", label, value));
                     }
                 }
                 Output::Percentile(percentile) => match percentile {
                     Percentile::Minimum => {
-                        data.push(format!("{}/minimum/value: {}", label, value));
+                        data.push(format!("{}: {}", join(label, "minimum/value"), value));
                     }
                     Percentile::Maximum => {
-                        data.push(format!("{}/maximum/value: {}", label, value));
+                        data.push(format!("{}: {}", join(label, "maximum/value"), value));
                     }
                     _ => {
-                        data.push(format!("{}/histogram/{}: {}", label, percentile, value));
+                        data.push(format!(
+                            "{}: {}",
+                            join(label, &format!("histogram/{}", percentile)),
+                            value
+                        ));
                     }
                 },
                 Output::MaxPointTime => {
-                    // we have point's ns since X and current timespec and current ns sinc X
-                    let point_ns = value;
-                    let now_timespec = time::get_time();
-                    let now_ns = time::precise_time_ns();
-
-                    // find the number of NS in the past for point
-                    let delta_ns = now_ns - point_ns;
-                    let point_timespec =
-                        now_timespec - time::Duration::nanoseconds(delta_ns as i64);
-
-                    // convert to UTC
-                    let point_utc = time::at_utc(point_timespec);
-                    // calculate offset from the top of the minute
-                    let offset = point_utc.tm_sec as u64 * 1_000_000_000 + point_utc.tm_nsec as u64;
-                    let offset_ms = (offset as f64 / 1_000_000.0).floor() as u64;
-                    data.push(format!("{}/maximum/offset_ms: {}", label, offset_ms));
+                    data.push(format!(
+                        "{}: {}",
+                        join(label, "maximum/offset_ms"),
+                        offset_ms(value)
+                    ));
                 }
                 _ => {
                     continue;
@@ -206,39 +228,40 @@ impl Http {
             match output {
                 Output::Counter => {
                     if let Some(ref count_label) = self.count_label {
-                        data.push(format!("\"{}/{}\": {}", label, count_label, value));
+                        data.push(format!("{}: {}", quote(&join(label, count_label)), value));
                     } else {
-                        data.push(format!("{}: {}", label, value));
+                        data.push(format!("{}: {}", quote(label), value));
                     }
                 }
                 Output::Percentile(percentile) => match percentile {
                     Percentile::Minimum => {
-                        data.push(format!("\"{}/minimum/value\": {}", label, value));
+                        data.push(format!(
+                            "{}: {}",
+                            quote(&join(label, "minimum/value")),
+                            value
+                        ));
                     }
                     Percentile::Maximum => {
-                        data.push(format!("\"{}/maximum/value\": {}", label, value));
+                        data.push(format!(
+                            "{}: {}",
+                            quote(&join(label, "maximum/value")),
+                            value
+                        ));
                     }
                     _ => {
-                        data.push(format!("\"{}/histogram/{}\": {}", label, percentile, value));
+                        data.push(format!(
+                            "{}: {}",
+                            quote(&join(label, &format!("histogram/{}", percentile))),
+                            value
+                        ));
                     }
                 },
                 Output::MaxPointTime => {
-                    // we have point's ns since X and current timespec and current ns since X
-                    let point_ns = value;
-                    let now_timespec = time::get_time();
-                    let now_ns = time::precise_time_ns();
-
-                    // find the number of NS in the past for point
-                    let delta_ns = now_ns - point_ns;
-                    let point_timespec =
-                        now_timespec - time::Duration::nanoseconds(delta_ns as i64);
-
-                    // convert to UTC
-                    let point_utc = time::at_utc(point_timespec);
-                    // calculate offset from the top of the minute
-                    let offset = point_utc.tm_sec as u64 * 1_000_000_000 + point_utc.tm_nsec as u64;
-                    let offset_ms = (offset as f64 / 1_000_000.0).floor() as u64;
-                    data.push(format!("\"{}/maximum/offset_ms\": {}", label, offset_ms));
+                    data.push(format!(
+                        "{}: {}",
+                        quote(&join(label, "maximum/offset_ms")),
+                        offset_ms(value)
+                    ));
                 }
                 _ => {
                     continue;
@@ -260,3 +283,8 @@ impl Http {
         content
     }
 }
+
+/// Quotes a label as a JSON string, escaping the quotes of any label values
+fn quote(label: &str) -> String {
+    json::stringify(label)
+}
@@ -0,0 +1,211 @@
+// Copyright 2019 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use super::Unit;
+use crate::samplers::Statistic;
+
+use std::fmt;
+
+/// Builds the label of a channel from a metric name and key/value labels,
+/// such as `disk/bandwidth/read{device="sda"}`. The labels are sorted by key
+/// so the same labels always produce the same channel
+pub fn channel(name: &str, labels: &[(&str, &str)]) -> String {
+    if labels.is_empty() {
+        return name.to_string();
+    }
+    let mut labels = labels.to_vec();
+    labels.sort();
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(key, value)| format!("{}=\"{}\"", key, escape(value)))
+        .collect();
+    format!("{}{{{}}}", name, labels.join(","))
+}
+
+/// Splits the label of a channel into its metric name and key/value labels
+pub fn parse(channel: &str) -> (&str, Vec<(String, String)>) {
+    let start = match channel.find('{') {
+        Some(start) if channel.ends_with('}') => start,
+        _ => return (channel, Vec::new()),
+    };
+    let name = &channel[..start];
+    let mut labels = Vec::new();
+    let mut chars = channel[(start + 1)..(channel.len() - 1)].chars();
+    loop {
+        let key: String = chars
+            .by_ref()
+            .take_while(|c| *c != '=')
+            .filter(|c| *c != ',')
+            .collect();
+        if key.is_empty() || chars.next() != Some('"') {
+            break;
+        }
+        let mut value = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => break,
+                '\\' => match chars.next() {
+                    Some('n') => value.push('\n'),
+                    Some(c) => value.push(c),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        labels.push((key, value));
+    }
+    (name, labels)
+}
+
+/// Escapes a label value as in the Prometheus text format
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Inserts a suffix between the metric name and labels of a channel, for
+/// the flat formats which append outputs to the name:
+/// `disk/bandwidth/read/count{device="sda"}`
+pub fn join(channel: &str, suffix: &str) -> String {
+    match channel.find('{') {
+        Some(start) => format!("{}/{}{}", &channel[..start], suffix, &channel[start..]),
+        None => format!("{}/{}", channel, suffix),
+    }
+}
+
+/// A `Statistic` with key/value labels which identify the entity it
+/// measures, such as a disk or network interface
+pub struct Labeled<T> {
+    statistic: T,
+    scope: Option<String>,
+    labels: Vec<(String, String)>,
+}
+
+impl<T: Statistic> Labeled<T> {
+    pub fn new(statistic: T) -> Self {
+        Self {
+            statistic,
+            scope: None,
+            labels: Vec::new(),
+        }
+    }
+
+    /// Insert a scope after the first component of the metric name, so the
+    /// series of each entity are named apart from the total across entities:
+    /// `disk/per_device/bandwidth/read`
+    pub fn with_scope<S: ToString>(mut self, scope: S) -> Self {
+        self.scope = Some(scope.to_string());
+        self
+    }
+
+    /// Add a label, replacing any previous value for the key
+    pub fn with_label<K: ToString, V: ToString>(mut self, key: K, value: V) -> Self {
+        let key = key.to_string();
+        self.labels.retain(|(k, _)| *k != key);
+        self.labels.push((key, value.to_string()));
+        self
+    }
+}
+
+impl<T: Statistic> fmt::Display for Labeled<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let labels: Vec<(&str, &str)> = self
+            .labels
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        let name = self.statistic.to_string();
+        let name = match self.scope {
+            Some(ref scope) => match name.find('/') {
+                Some(end) => format!("{}/{}{}", &name[..end], scope, &name[end..]),
+                None => format!("{}/{}", name, scope),
+            },
+            None => name,
+        };
+        write!(f, "{}", channel(&name, &labels))
+    }
+}
+
+impl<T: Statistic> Statistic for Labeled<T> {
+    fn description(&self) -> &str {
+        self.statistic.description()
+    }
+
+    fn unit(&self) -> Option<Unit> {
+        self.statistic.unit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::Metric;
+
+    #[test]
+    fn build_channel() {
+        assert_eq!(channel("cpu/user", &[]), "cpu/user");
+        assert_eq!(
+            channel("cpu/user", &[("node", "0"), ("cpu", "3")]),
+            "cpu/user{cpu=\"3\",node=\"0\"}"
+        );
+        assert_eq!(
+            channel("memcache/version", &[("value", "a \"b\"")]),
+            "memcache/version{value=\"a \\\"b\\\"\"}"
+        );
+    }
+
+    #[test]
+    fn parse_channel() {
+        assert_eq!(parse("cpu/user"), ("cpu/user", Vec::new()));
+        assert_eq!(
+            parse("cpu/user{cpu=\"3\",node=\"0\"}"),
+            (
+                "cpu/user",
+                vec![
+                    ("cpu".to_string(), "3".to_string()),
+                    ("node".to_string(), "0".to_string())
+                ]
+            )
+        );
+        let channel = channel("memcache/version", &[("value", "a \"b\",{c}")]);
+        assert_eq!(
+            parse(&channel),
+            (
+                "memcache/version",
+                vec![("value".to_string(), "a \"b\",{c}".to_string())]
+            )
+        );
+    }
+
+    #[test]
+    fn join_suffix() {
+        assert_eq!(join("cpu/user", "count"), "cpu/user/count");
+        assert_eq!(
+            join("disk/bandwidth/read{device=\"sda\"}", "count"),
+            "disk/bandwidth/read/count{device=\"sda\"}"
+        );
+    }
+
+    #[test]
+    fn labeled_statistic() {
+        let statistic = Labeled::new(Metric::new("disk/bandwidth/read", "bytes read"))
+            .with_label("device", "sda")
+            .with_label("device", "sdb");
+        assert_eq!(statistic.to_string(), "disk/bandwidth/read{device=\"sdb\"}");
+        assert_eq!(statistic.description(), "bytes read");
+    }
+
+    #[test]
+    fn scoped_statistic() {
+        let statistic = Metric::new("disk/bandwidth/read", "bytes read").per("device", "sda");
+        assert_eq!(
+            statistic.to_string(),
+            "disk/per_device/bandwidth/read{device=\"sda\"}"
+        );
+        let statistic = Labeled::new(Metric::new("uptime", "seconds")).with_scope("per_host");
+        assert_eq!(statistic.to_string(), "uptime/per_host");
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright 2019 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use lazy_static::lazy_static;
+use time::Timespec;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::RwLock;
+
+lazy_static! {
+    static ref METADATA: RwLock<HashMap<String, Metadata>> = RwLock::new(HashMap::new());
+}
+
+/// The type of a channel, which determines how it is exposed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    /// A monotonically increasing value
+    Counter,
+    /// A value which may go up or down
+    Gauge,
+    /// A distribution of values, only exposed as percentiles
+    Distribution,
+}
+
+/// The unit of the values recorded into a channel
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Unit {
+    Bytes,
+    Nanoseconds,
+    Seconds,
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Unit::Bytes => write!(f, "bytes"),
+            Unit::Nanoseconds => write!(f, "nanoseconds"),
+            Unit::Seconds => write!(f, "seconds"),
+        }
+    }
+}
+
+/// Describes a channel for the exposition formats, which only see the label
+/// of each reading
+#[derive(Clone, Debug)]
+pub struct Metadata {
+    kind: Kind,
+    unit: Option<Unit>,
+    description: String,
+    created: Timespec,
+}
+
+impl Metadata {
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    pub fn unit(&self) -> Option<Unit> {
+        self.unit
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// The time at which the channel was registered
+    pub fn created(&self) -> Timespec {
+        self.created
+    }
+}
+
+/// Record the metadata for a label, replacing any previous metadata
+pub fn describe(label: &str, kind: Kind, unit: Option<Unit>, description: &str) {
+    let mut metadata = METADATA.write().unwrap();
+    metadata.insert(
+        label.to_string(),
+        Metadata {
+            kind,
+            unit,
+            description: description.to_string(),
+            created: time::get_time(),
+        },
+    );
+}
+
+/// Remove the metadata for a label whose channel has been deleted
+pub fn forget(label: &str) {
+    let mut metadata = METADATA.write().unwrap();
+    metadata.remove(label);
+}
+
+/// Returns the metadata for a label if it has been described
+pub fn metadata(label: &str) -> Option<Metadata> {
+    let metadata = METADATA.read().unwrap();
+    metadata.get(label).cloned()
+}
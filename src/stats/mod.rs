@@ -4,15 +4,69 @@
 
 #![allow(dead_code)]
 
+mod buckets;
+mod exposition;
 mod http;
+mod labels;
+mod metadata;
+mod openmetrics;
+mod prometheus;
 
 pub use self::http::Http;
-use std::time::Duration;
+pub use self::labels::{channel, Labeled};
+pub use self::metadata::Unit;
+
+use self::labels::join;
+use self::metadata::{describe, forget, Kind};
+use crate::samplers::Statistic;
 
 use metrics::*;
 
+use std::fmt;
 use std::fs::{File, OpenOptions};
 use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// A `Statistic` whose label is only known at runtime, such as the stats
+/// reported by memcache or the health of each sampler
+pub struct Metric {
+    label: String,
+    description: String,
+    unit: Option<Unit>,
+}
+
+impl Metric {
+    pub fn new<T: ToString, U: ToString>(label: T, description: U) -> Self {
+        Self {
+            label: label.to_string(),
+            description: description.to_string(),
+            unit: None,
+        }
+    }
+
+    /// Set the unit of the values recorded for the metric
+    pub fn with_unit(mut self, unit: Unit) -> Self {
+        self.unit = Some(unit);
+        self
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.label)
+    }
+}
+
+impl Statistic for Metric {
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn unit(&self) -> Option<Unit> {
+        self.unit
+    }
+}
 
 pub struct StatsLog {
     file: File,
@@ -44,39 +98,32 @@ impl StatsLog {
             match output {
                 Output::Counter => {
                     if let Some(ref count_label) = self.count_label {
-                        data.push(format!("{}/{}: {}", label, count_label, value));
+                        data.push(format!("{}: {}", join(label, count_label), value));
                     } else {
                         data.push(format!("{}: {}", label, value));
                     }
                 }
                 Output::Percentile(percentile) => match percentile {
                     Percentile::Minimum => {
-                        data.push(format!("{}/minimum/value: {}", label, value));
+                        data.push(format!("{}: {}", join(label, "minimum/value"), value));
                     }
                     Percentile::Maximum => {
-                        data.push(format!("{}/maximum/value: {}", label, value));
+                        data.push(format!("{}: {}", join(label, "maximum/value"), value));
                     }
                     _ => {
-                        data.push(format!("{}/histogram/{}: {}", label, percentile, value));
+                        data.push(format!(
+                            "{}: {}",
+                            join(label, &format!("histogram/{}", percentile)),
+                            value
+                        ));
                     }
                 },
                 Output::MaxPointTime => {
-                    // we have point's ns since X and current timespec and current ns sinc X
-                    let point_ns = value;
-                    let now_timespec = time::get_time();
-                    let now_ns = time::precise_time_ns();
-
-                    // find the number of NS in the past for point
-                    let delta_ns = now_ns - point_ns;
-                    let point_timespec =
-                        now_timespec - time::Duration::nanoseconds(delta_ns as i64);
-
-                    // convert to UTC
-                    let point_utc = time::at_utc(point_timespec);
-                    // calculate offset from the top of the minute
-                    let offset = point_utc.tm_sec as u64 * 1_000_000_000 + point_utc.tm_nsec as u64;
-                    let offset_ms = (offset as f64 / 1_000_000.0).floor() as usize;
-                    data.push(format!("{}/maximum/offset_ms: {}", label, offset_ms));
+                    data.push(format!(
+                        "{}: {}",
+                        join(label, "maximum/offset_ms"),
+                        offset_ms(value)
+                    ));
                 }
                 _ => {
                     continue;
@@ -90,18 +137,41 @@ impl StatsLog {
         let _ = self.file.write(b"\n");
     }
 
-    pub fn run(&mut self) {
-        let time = time::now_utc();
-        let offset = time.tm_sec;
-        let delay = 60 - offset;
-        std::thread::sleep(std::time::Duration::new(delay as u64, 0));
-        loop {
-            std::thread::sleep(std::time::Duration::new(60, 0));
-            self.print();
+    /// Log the stats at the top of each minute until `shutdown` is set. The
+    /// stats are logged once more before returning, so the final partial
+    /// minute is not lost
+    pub fn run(&mut self, shutdown: &AtomicBool) {
+        let mut next = (time::get_time().sec / 60 + 1) * 60;
+        while !shutdown.load(Ordering::Relaxed) {
+            if time::get_time().sec >= next {
+                self.print();
+                next += 60;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
         }
+        self.print();
+        let _ = self.file.flush();
     }
 }
 
+/// Converts the time of a point, as returned by `time::precise_time_ns()`, to
+/// its offset in milliseconds from the top of the minute
+fn offset_ms(point_ns: u64) -> u64 {
+    // we have point's ns since X and current timespec and current ns since X
+    let now_timespec = time::get_time();
+    let now_ns = time::precise_time_ns();
+
+    // find the number of NS in the past for point
+    let delta_ns = now_ns - point_ns;
+    let point_timespec = now_timespec - time::Duration::nanoseconds(delta_ns as i64);
+
+    // convert to UTC
+    let point_utc = time::at_utc(point_timespec);
+    // calculate offset from the top of the minute
+    let offset = point_utc.tm_sec as u64 * 1_000_000_000 + point_utc.tm_nsec as u64;
+    (offset as f64 / 1_000_000.0).floor() as u64
+}
+
 pub fn record_counter<T>(recorder: &Recorder<AtomicU32>, label: T, time: u64, value: u64)
 where
     T: ToString,
@@ -125,72 +195,129 @@ pub fn record_distribution<T>(
 ) where
     T: ToString,
 {
-    recorder.record(
-        label.to_string(),
-        Measurement::Distribution { time, value, count },
-    );
+    let label = label.to_string();
+    buckets::record(&label, value, u64::from(count));
+    recorder.record(label, Measurement::Distribution { time, value, count });
+}
+
+/// Register a channel without a histogram, which only exposes its current
+/// value. `source` must be either a counter or a gauge
+pub fn register_value<T>(recorder: &Recorder<AtomicU32>, statistic: T, source: Source)
+where
+    T: Statistic,
+{
+    let label = statistic.to_string();
+    let kind = match source {
+        Source::Counter => Kind::Counter,
+        _ => Kind::Gauge,
+    };
+    describe(&label, kind, statistic.unit(), statistic.description());
+    recorder.add_channel(label.clone(), source, None);
+    recorder.add_output(label, Output::Counter);
 }
 
 pub fn register_counter<T>(
     recorder: &Recorder<AtomicU32>,
-    label: T,
+    statistic: T,
     max: u64,
     precision: u32,
     duration: Duration,
     percentiles: &[Percentile],
 ) where
-    T: ToString,
+    T: Statistic,
 {
+    let label = statistic.to_string();
+    describe(
+        &label,
+        Kind::Counter,
+        statistic.unit(),
+        statistic.description(),
+    );
     recorder.add_channel(
-        label.to_string(),
+        label.clone(),
         Source::Counter,
         Some(Histogram::new(max, precision, Some(duration), None)),
     );
-    recorder.add_output(label.to_string(), Output::Counter);
-    recorder.add_output(label.to_string(), Output::MaxPointTime);
+    recorder.add_output(label.clone(), Output::Counter);
+    recorder.add_output(label.clone(), Output::MaxPointTime);
     for percentile in percentiles {
-        recorder.add_output(label.to_string(), Output::Percentile(*percentile));
+        recorder.add_output(label.clone(), Output::Percentile(*percentile));
     }
 }
 
 pub fn register_gauge<T>(
     recorder: &Recorder<AtomicU32>,
-    label: T,
+    statistic: T,
     max: u64,
     precision: u32,
     duration: Duration,
     percentiles: &[Percentile],
 ) where
-    T: ToString,
+    T: Statistic,
 {
+    let label = statistic.to_string();
+    describe(
+        &label,
+        Kind::Gauge,
+        statistic.unit(),
+        statistic.description(),
+    );
     recorder.add_channel(
-        label.to_string(),
+        label.clone(),
         Source::Gauge,
         Some(Histogram::new(max, precision, Some(duration), None)),
     );
-    recorder.add_output(label.to_string(), Output::Counter);
-    recorder.add_output(label.to_string(), Output::MaxPointTime);
+    recorder.add_output(label.clone(), Output::Counter);
+    recorder.add_output(label.clone(), Output::MaxPointTime);
     for percentile in percentiles {
-        recorder.add_output(label.to_string(), Output::Percentile(*percentile));
+        recorder.add_output(label.clone(), Output::Percentile(*percentile));
     }
 }
 
 pub fn register_distribution<T>(
     recorder: &Recorder<AtomicU32>,
-    label: T,
+    statistic: T,
     max: u64,
     precision: u32,
     duration: Duration,
     percentiles: &[Percentile],
 ) where
-    T: ToString,
+    T: Statistic,
 {
+    let label = statistic.to_string();
+    describe(
+        &label,
+        Kind::Distribution,
+        statistic.unit(),
+        statistic.description(),
+    );
+    buckets::register(&label, max);
     recorder.add_channel(
-        label.to_string(),
+        label.clone(),
         Source::Distribution,
         Some(Histogram::new(max, precision, Some(duration), None)),
     );
     for &percentile in percentiles {
-        recorder.add_output(label.to_string(), Output::Percentile(percentile));
+        recorder.add_output(label.clone(), Output::Percentile(percentile));
     }
 }
+
+/// Remove a channel along with its metadata
+pub fn delete_channel<T>(recorder: &Recorder<AtomicU32>, label: T)
+where
+    T: ToString,
+{
+    let label = label.to_string();
+    forget(&label);
+    recorder.delete_channel(label);
+}
+
+/// Remove a distribution channel along with its buckets and metadata
+pub fn delete_distribution<T>(recorder: &Recorder<AtomicU32>, label: T)
+where
+    T: ToString,
+{
+    let label = label.to_string();
+    buckets::deregister(&label);
+    delete_channel(recorder, label);
+}
@@ -0,0 +1,152 @@
+// Copyright 2019 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use super::exposition::{escape, families, format_labels, Type};
+
+use metrics::*;
+use time::Timespec;
+
+/// Media type requested by OpenMetrics clients in their `Accept` header
+pub const MEDIA_TYPE: &str = "application/openmetrics-text";
+
+/// Content type of the OpenMetrics text exposition format
+pub const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Encode the readings in the OpenMetrics text exposition format, version
+/// 1.0.0. Families are named as in the Prometheus format, with their unit as
+/// a suffix if they have one. Counters and summaries expose the time they were
+/// registered as `_created`
+pub fn encode(readings: &[Reading]) -> String {
+    render(
+        readings
+            .iter()
+            .map(|reading| (reading.label(), reading.output(), reading.value())),
+    )
+}
+
+fn render<'a, I>(readings: I) -> String
+where
+    I: Iterator<Item = (&'a str, Output, u64)>,
+{
+    let mut content = String::new();
+    for (name, family) in families(readings) {
+        let name = match family.unit {
+            Some(unit) if !name.ends_with(&format!("_{}", unit)) => format!("{}_{}", name, unit),
+            _ => name,
+        };
+        let kind = match family.kind {
+            Type::Counter => "counter",
+            Type::Gauge => "gauge",
+            Type::Summary => "summary",
+            Type::Unknown => "unknown",
+        };
+        content += &format!("# TYPE {} {}\n", name, kind);
+        if let Some(unit) = family.unit {
+            content += &format!("# UNIT {} {}\n", name, unit);
+        }
+        content += &format!("# HELP {} {}\n", name, escape(&family.help));
+        // each channel in the family has one `_created`, following its samples
+        let mut created: Vec<(&Vec<(String, String)>, Timespec)> = Vec::new();
+        for sample in &family.samples {
+            let labels = sample.labels();
+            if family.kind == Type::Counter {
+                content += &format!("{}_total{} {}\n", name, labels, sample.value);
+            } else {
+                content += &format!("{}{} {}\n", name, labels, sample.value);
+            }
+            if let Some(time) = sample.created {
+                if !created.iter().any(|(labels, _)| **labels == sample.labels) {
+                    created.push((&sample.labels, time));
+                }
+            }
+        }
+        if family.kind == Type::Counter || family.kind == Type::Summary {
+            for (labels, time) in created {
+                content += &format!(
+                    "{}_created{} {}.{:03}\n",
+                    name,
+                    format_labels(labels, None),
+                    time.sec,
+                    time.nsec / 1_000_000
+                );
+            }
+        }
+    }
+    content += "# EOF\n";
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::metadata::{describe, metadata, Kind, Unit};
+
+    #[test]
+    fn render_families() {
+        describe(
+            "openmetrics/counter",
+            Kind::Counter,
+            Some(Unit::Bytes),
+            "a counter",
+        );
+        describe(
+            "openmetrics/gauge",
+            Kind::Gauge,
+            Some(Unit::Seconds),
+            "a gauge",
+        );
+        describe(
+            "openmetrics/distribution_nanoseconds",
+            Kind::Distribution,
+            Some(Unit::Nanoseconds),
+            "a distribution",
+        );
+        let created = |label| {
+            let created = metadata(label).unwrap().created();
+            format!("{}.{:03}", created.sec, created.nsec / 1_000_000)
+        };
+        let readings = vec![
+            ("openmetrics/counter", Output::Counter, 42),
+            (
+                "openmetrics/counter",
+                Output::Percentile(Percentile::p50),
+                2,
+            ),
+            ("openmetrics/gauge", Output::Counter, 7),
+            (
+                "openmetrics/distribution_nanoseconds",
+                Output::Percentile(Percentile::p50),
+                100,
+            ),
+            ("openmetrics/unknown", Output::Counter, 1),
+        ];
+        let expected = format!(
+            "# TYPE openmetrics_counter_bytes counter\n\
+             # UNIT openmetrics_counter_bytes bytes\n\
+             # HELP openmetrics_counter_bytes a counter\n\
+             openmetrics_counter_bytes_total 42\n\
+             openmetrics_counter_bytes_created {counter}\n\
+             # TYPE openmetrics_counter_histogram summary\n\
+             # HELP openmetrics_counter_histogram a counter (percentiles of the secondly rate)\n\
+             openmetrics_counter_histogram{{quantile=\"0.5\"}} 2\n\
+             openmetrics_counter_histogram_created {counter}\n\
+             # TYPE openmetrics_distribution_nanoseconds summary\n\
+             # UNIT openmetrics_distribution_nanoseconds nanoseconds\n\
+             # HELP openmetrics_distribution_nanoseconds a distribution\n\
+             openmetrics_distribution_nanoseconds{{quantile=\"0.5\"}} 100\n\
+             openmetrics_distribution_nanoseconds_created {distribution}\n\
+             # TYPE openmetrics_gauge_seconds gauge\n\
+             # UNIT openmetrics_gauge_seconds seconds\n\
+             # HELP openmetrics_gauge_seconds a gauge\n\
+             openmetrics_gauge_seconds 7\n\
+             # TYPE openmetrics_unknown unknown\n\
+             # HELP openmetrics_unknown openmetrics/unknown\n\
+             openmetrics_unknown 1\n\
+             # EOF\n",
+            counter = created("openmetrics/counter"),
+            distribution = created("openmetrics/distribution_nanoseconds"),
+        );
+        assert_eq!(render(readings.into_iter()), expected);
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright 2019 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use super::buckets::Buckets;
+use super::exposition::{escape, families, format_labels, sanitize, Type};
+use super::labels::parse;
+use super::metadata::metadata;
+
+use metrics::*;
+
+use std::collections::BTreeMap;
+
+/// Content type of the Prometheus text exposition format
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Encode the readings in the Prometheus text exposition format, version
+/// 0.0.4. See `exposition::families()` for how readings are named
+pub fn encode(readings: &[Reading]) -> String {
+    render(
+        readings
+            .iter()
+            .map(|reading| (reading.label(), reading.output(), reading.value())),
+    )
+}
+
+fn render<'a, I>(readings: I) -> String
+where
+    I: Iterator<Item = (&'a str, Output, u64)>,
+{
+    let mut content = String::new();
+    for (name, family) in families(readings) {
+        let kind = match family.kind {
+            Type::Counter => "counter",
+            Type::Gauge => "gauge",
+            Type::Summary => "summary",
+            Type::Unknown => "untyped",
+        };
+        content += &format!("# HELP {} {}\n", name, escape(&family.help));
+        content += &format!("# TYPE {} {}\n", name, kind);
+        for sample in family.samples {
+            content += &format!("{}{} {}\n", name, sample.labels(), sample.value);
+        }
+    }
+    content
+}
+
+/// Encode the buckets of distribution channels as Prometheus histograms,
+/// named `(name)_histogram` with cumulative `_bucket` series for each upper
+/// bound `le`
+pub fn encode_buckets(buckets: &[(String, Buckets)]) -> String {
+    // group the channels by their metric name, as with the other families
+    let mut families: BTreeMap<String, (String, Vec<(Vec<(String, String)>, &Buckets)>)> =
+        BTreeMap::new();
+    for (label, buckets) in buckets {
+        let (name, labels) = parse(label);
+        let help = metadata(label)
+            .map(|metadata| metadata.description().to_string())
+            .unwrap_or_else(|| name.to_string());
+        families
+            .entry(format!("{}_histogram", sanitize(name)))
+            .or_insert_with(|| (help, Vec::new()))
+            .1
+            .push((labels, buckets));
+    }
+
+    let mut content = String::new();
+    for (name, (help, channels)) in families {
+        content += &format!("# HELP {} {}\n", name, escape(&help));
+        content += &format!("# TYPE {} histogram\n", name);
+        for (labels, buckets) in channels {
+            let mut cumulative = 0;
+            for (bound, count) in buckets.buckets() {
+                cumulative += count;
+                let bound = bound.map_or_else(|| "+Inf".to_string(), |bound| bound.to_string());
+                let labels = format_labels(&labels, Some(("le", &bound)));
+                content += &format!("{}_bucket{} {}\n", name, labels, cumulative);
+            }
+            let labels = format_labels(&labels, None);
+            content += &format!("{}_sum{} {}\n", name, labels, buckets.sum());
+            content += &format!("{}_count{} {}\n", name, labels, buckets.count());
+        }
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::buckets;
+    use crate::stats::metadata::{describe, Kind};
+
+    #[test]
+    fn render_families() {
+        describe("prometheus/counter", Kind::Counter, None, "a counter");
+        describe(
+            "prometheus/gauge",
+            Kind::Gauge,
+            None,
+            "a gauge\nwith two lines",
+        );
+        describe(
+            "prometheus/distribution",
+            Kind::Distribution,
+            None,
+            "a distribution",
+        );
+        let readings = vec![
+            ("prometheus/counter", Output::Counter, 42),
+            ("prometheus/counter", Output::Percentile(Percentile::p99), 4),
+            ("prometheus/counter", Output::Percentile(Percentile::p50), 2),
+            ("prometheus/gauge", Output::Counter, 7),
+            (
+                "prometheus/distribution",
+                Output::Percentile(Percentile::p50),
+                100,
+            ),
+            ("prometheus/unknown", Output::Counter, 1),
+        ];
+        let expected = "# HELP prometheus_counter a counter\n\
+                        # TYPE prometheus_counter counter\n\
+                        prometheus_counter 42\n\
+                        # HELP prometheus_counter_histogram a counter (percentiles of the secondly rate)\n\
+                        # TYPE prometheus_counter_histogram summary\n\
+                        prometheus_counter_histogram{quantile=\"0.5\"} 2\n\
+                        prometheus_counter_histogram{quantile=\"0.99\"} 4\n\
+                        # HELP prometheus_distribution a distribution\n\
+                        # TYPE prometheus_distribution summary\n\
+                        prometheus_distribution{quantile=\"0.5\"} 100\n\
+                        # HELP prometheus_gauge a gauge\\nwith two lines\n\
+                        # TYPE prometheus_gauge gauge\n\
+                        prometheus_gauge 7\n\
+                        # HELP prometheus_unknown prometheus/unknown\n\
+                        # TYPE prometheus_unknown untyped\n\
+                        prometheus_unknown 1\n";
+        assert_eq!(render(readings.into_iter()), expected);
+    }
+
+    #[test]
+    fn render_labels() {
+        let sda = "prometheus/labeled{device=\"sda\"}";
+        let sdb = "prometheus/labeled{device=\"sdb\"}";
+        describe(sda, Kind::Counter, None, "a labeled counter");
+        describe(sdb, Kind::Counter, None, "a labeled counter");
+        let readings = vec![
+            (sdb, Output::Counter, 2),
+            (sda, Output::Counter, 1),
+            (sda, Output::Percentile(Percentile::p50), 3),
+        ];
+        let expected = "# HELP prometheus_labeled a labeled counter\n\
+                        # TYPE prometheus_labeled counter\n\
+                        prometheus_labeled{device=\"sda\"} 1\n\
+                        prometheus_labeled{device=\"sdb\"} 2\n\
+                        # HELP prometheus_labeled_histogram a labeled counter (percentiles of the secondly rate)\n\
+                        # TYPE prometheus_labeled_histogram summary\n\
+                        prometheus_labeled_histogram{device=\"sda\",quantile=\"0.5\"} 3\n";
+        assert_eq!(render(readings.into_iter()), expected);
+    }
+
+    #[test]
+    fn encode_histogram() {
+        describe(
+            "prometheus/buckets",
+            Kind::Distribution,
+            None,
+            "a distribution",
+        );
+        buckets::register("prometheus/buckets", 5);
+        buckets::record("prometheus/buckets", 2, 3);
+        buckets::record("prometheus/buckets", 4, 1);
+        buckets::record("prometheus/buckets", 6, 1);
+        let snapshot: Vec<(String, Buckets)> = buckets::snapshot()
+            .into_iter()
+            .filter(|(label, _)| label == "prometheus/buckets")
+            .collect();
+        let expected = "# HELP prometheus_buckets_histogram a distribution\n\
+                        # TYPE prometheus_buckets_histogram histogram\n\
+                        prometheus_buckets_histogram_bucket{le=\"1\"} 0\n\
+                        prometheus_buckets_histogram_bucket{le=\"2\"} 3\n\
+                        prometheus_buckets_histogram_bucket{le=\"5\"} 4\n\
+                        prometheus_buckets_histogram_bucket{le=\"+Inf\"} 5\n\
+                        prometheus_buckets_histogram_sum 16\n\
+                        prometheus_buckets_histogram_count 5\n";
+        assert_eq!(encode_buckets(&snapshot), expected);
+    }
+}